@@ -1,4 +1,4 @@
-use soroban_sdk::{contracttype, Address, Symbol, Vec};
+use soroban_sdk::{contracttype, Address, BytesN, String, Symbol, Vec};
 
 /// Represents the current state of a payment in its lifecycle
 #[contracttype]
@@ -14,6 +14,8 @@ pub enum PaymentStatus {
     Refunded,
     /// Payment cancelled before escrow
     Cancelled,
+    /// Escrow frozen pending an arbitrator's ruling
+    Disputed,
 }
 
 /// Conditions that must be met before escrow funds can be released
@@ -74,6 +76,159 @@ pub struct FeeStructure {
     pub performance_bonus: i128,
 }
 
+/// One funding party's part of a split payment
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayerShare {
+    /// Party funding this share, e.g. an insurer, hospital or patient
+    pub payer: Address,
+    /// Amount this party owes
+    pub amount: i128,
+    /// Whether this party has paid its share into escrow
+    pub funded: bool,
+}
+
+/// Transfer stage that releases an escrow milestone
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MilestoneTrigger {
+    /// Unit dispatched to the hospital
+    UnitInTransit(u64),
+    /// Unit delivery confirmed by the hospital
+    UnitDelivered(u64),
+    /// Request fulfilled
+    RequestFulfilled(u64),
+}
+
+/// Share of an escrow released automatically when its trigger fires
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EscrowMilestone {
+    /// Lifecycle event that releases this milestone
+    pub trigger: MilestoneTrigger,
+    /// Share of the payment amount, in basis points
+    pub share_bps: u32,
+    /// Timestamp when the milestone was released (if applicable)
+    pub released_at: Option<u64>,
+}
+
+/// Ruling an arbitrator can make on a disputed payment
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisputeOutcome {
+    /// Release the full escrow to the payee
+    Release,
+    /// Refund the full escrow to the payer
+    Refund,
+    /// Pay the given amount to the payee and refund the rest
+    Split(i128),
+}
+
+/// Arbitrator's ruling, recorded on the dispute
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisputeRuling {
+    /// Arbitrator who ruled
+    pub arbitrator: Address,
+    /// Outcome ruled
+    pub outcome: DisputeOutcome,
+    /// Amount released to the payee
+    pub payee_amount: i128,
+    /// Amount refunded to the payer
+    pub payer_amount: i128,
+    /// Reason given for the ruling
+    pub reason: String,
+    /// Timestamp of the ruling
+    pub ruled_at: u64,
+}
+
+/// Dispute opened by a payer against an escrowed payment
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentDispute {
+    /// Disputed payment ID
+    pub payment_id: u64,
+    /// Payer who opened the dispute
+    pub opened_by: Address,
+    /// Hashes of evidence submitted by either party
+    pub evidence: Vec<BytesN<32>>,
+    /// Timestamp when the dispute was opened
+    pub opened_at: u64,
+    /// Timestamp of the ruling, once made
+    pub ruled_at: Option<u64>,
+}
+
+/// Fees kept from an escrow refunded because its request ended unfulfilled
+#[contracttype]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CancellationPolicy {
+    /// Share kept when the request is cancelled while pending, in basis points
+    pub pending_fee_bps: u32,
+    /// Share kept when the request is cancelled after approval, in basis points
+    pub approved_fee_bps: u32,
+}
+
+/// Delivery-performance incentive for requests of one urgency level
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BonusPolicy {
+    /// Share of the time between request and deadline within which delivery
+    /// earns the bonus, in basis points
+    pub target_window_bps: u32,
+    /// Bonus for delivery within the target window, in basis points of the payment
+    pub bonus_bps: u32,
+    /// Penalty for delivery after the deadline, in basis points of the payment
+    pub penalty_bps: u32,
+}
+
+/// One published price in a bank's schedule for a product and blood type
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriceEntry {
+    /// Price charged per delivered unit
+    pub price_per_unit: i128,
+    /// Time from which the price applies
+    pub effective_from: u64,
+    /// Time the price was published
+    pub published_at: u64,
+}
+
+/// Running billing totals between one hospital and one bank for a period
+#[contracttype]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BillingLedger {
+    /// Units delivered from the bank to the hospital
+    pub units_delivered: u32,
+    /// Amounts placed in escrow, plus performance bonuses paid on top
+    pub gross: i128,
+    /// Fees charged under the fee structure
+    pub fees: i128,
+    /// Amounts paid out to the bank
+    pub paid: i128,
+    /// Amounts refunded to payers
+    pub refunds: i128,
+}
+
+/// Closed billing period, stored once and never changed
+#[contracttype]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BillingStatement {
+    /// Billed hospital
+    pub hospital: Address,
+    /// Paid bank
+    pub bank: Address,
+    /// Billing period index
+    pub period: u64,
+    /// Totals for the period
+    pub ledger: BillingLedger,
+    /// Escrowed amounts neither paid out nor refunded when closed
+    pub outstanding: i128,
+    /// Timestamp when the period was closed
+    pub closed_at: u64,
+    /// SHA-256 of the statement's parties, period and totals
+    pub hash: BytesN<32>,
+}
+
 /// Additional metadata for transaction tracking
 ///
 /// Note: Soroban Symbols have strict constraints:
@@ -120,9 +275,14 @@ impl Payment {
             (PaymentStatus::Pending, PaymentStatus::Escrowed) => true,
             (PaymentStatus::Pending, PaymentStatus::Cancelled) => true,
 
-            // Escrowed can go to Completed or Refunded
+            // Escrowed can go to Completed, Refunded or Disputed
             (PaymentStatus::Escrowed, PaymentStatus::Completed) => true,
             (PaymentStatus::Escrowed, PaymentStatus::Refunded) => true,
+            (PaymentStatus::Escrowed, PaymentStatus::Disputed) => true,
+
+            // Disputed is settled by the arbitrator's ruling
+            (PaymentStatus::Disputed, PaymentStatus::Completed) => true,
+            (PaymentStatus::Disputed, PaymentStatus::Refunded) => true,
 
             // Terminal states cannot transition
             (PaymentStatus::Completed, _) => false,
@@ -177,6 +337,202 @@ impl EscrowAccount {
 
         true
     }
+
+    /// Splits the locked amount per a dispute ruling into (payee, payer) amounts
+    pub fn settle(&self, outcome: &DisputeOutcome) -> Result<(i128, i128), PaymentError> {
+        match outcome {
+            DisputeOutcome::Release => Ok((self.locked_amount, 0)),
+            DisputeOutcome::Refund => Ok((0, self.locked_amount)),
+            DisputeOutcome::Split(payee_amount) => {
+                // A split must leave something for each side
+                if *payee_amount <= 0 || *payee_amount >= self.locked_amount {
+                    return Err(PaymentError::InvalidRuling);
+                }
+                Ok((*payee_amount, self.locked_amount - payee_amount))
+            }
+        }
+    }
+}
+
+impl PayerShare {
+    /// Validates the shares of a new split payment to `payee`
+    pub fn validate_shares(shares: &Vec<PayerShare>, payee: &Address) -> Result<(), PaymentError> {
+        if shares.is_empty() {
+            return Err(PaymentError::InvalidShares);
+        }
+        for (i, share) in shares.iter().enumerate() {
+            if share.amount <= 0 || share.funded || &share.payer == payee {
+                return Err(PaymentError::InvalidShares);
+            }
+            // Each party holds one share
+            if shares
+                .iter()
+                .skip(i + 1)
+                .any(|other| other.payer == share.payer)
+            {
+                return Err(PaymentError::InvalidShares);
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits `amount` across the funded shares in proportion to what each
+    /// paid; the last funded share takes any rounding remainder
+    pub fn apportion(shares: &Vec<PayerShare>, amount: i128) -> Vec<i128> {
+        let funded: i128 = shares.iter().filter(|s| s.funded).map(|s| s.amount).sum();
+        let last = shares.iter().rposition(|s| s.funded);
+
+        let mut parts = Vec::new(shares.env());
+        let mut remaining = amount;
+        for (i, share) in shares.iter().enumerate() {
+            let part = if !share.funded || funded == 0 {
+                0
+            } else if Some(i) == last {
+                remaining
+            } else {
+                amount * share.amount / funded
+            };
+            remaining -= part;
+            parts.push_back(part);
+        }
+        parts
+    }
+}
+
+impl EscrowMilestone {
+    /// Validates a milestone schedule
+    pub fn validate_schedule(milestones: &Vec<EscrowMilestone>) -> Result<(), PaymentError> {
+        let mut total_bps = 0u32;
+        for milestone in milestones.iter() {
+            if milestone.share_bps == 0 || milestone.released_at.is_some() {
+                return Err(PaymentError::InvalidMilestones);
+            }
+            total_bps = total_bps.saturating_add(milestone.share_bps);
+        }
+
+        // Shares may leave a remainder for manual release, but not exceed the whole
+        if milestones.is_empty() || total_bps > 10_000 {
+            return Err(PaymentError::InvalidMilestones);
+        }
+        Ok(())
+    }
+
+    /// Calculates the part of `amount` this milestone releases
+    pub fn share_of(&self, amount: i128) -> i128 {
+        amount * self.share_bps as i128 / 10_000
+    }
+}
+
+impl CancellationPolicy {
+    /// Validates cancellation fee shares
+    pub fn validate(&self) -> Result<(), PaymentError> {
+        if self.pending_fee_bps > 10_000 || self.approved_fee_bps > 10_000 {
+            return Err(PaymentError::InvalidFee);
+        }
+        Ok(())
+    }
+
+    /// Splits a refunded amount into (fee, refund), charging the approved
+    /// rate once the payee has started serving the request
+    pub fn split_refund(&self, amount: i128, service_started: bool) -> (i128, i128) {
+        let fee_bps = if service_started {
+            self.approved_fee_bps
+        } else {
+            self.pending_fee_bps
+        };
+        let fee = amount * fee_bps as i128 / 10_000;
+        (fee, amount - fee)
+    }
+}
+
+impl BonusPolicy {
+    /// Validates bonus policy shares
+    pub fn validate(&self) -> Result<(), PaymentError> {
+        if self.target_window_bps > 10_000 || self.bonus_bps > 10_000 || self.penalty_bps > 10_000 {
+            return Err(PaymentError::InvalidFee);
+        }
+        Ok(())
+    }
+
+    /// Calculates the (bonus, penalty) on `amount` for a request created at
+    /// `created_at`, due by `required_by` and delivered at `delivered_at`
+    pub fn adjustment(
+        &self,
+        amount: i128,
+        created_at: u64,
+        required_by: u64,
+        delivered_at: u64,
+    ) -> (i128, i128) {
+        if delivered_at > required_by {
+            return (0, amount * self.penalty_bps as i128 / 10_000);
+        }
+
+        let allowed = required_by.saturating_sub(created_at) as u128;
+        let target = allowed * self.target_window_bps as u128 / 10_000;
+        if (delivered_at.saturating_sub(created_at) as u128) <= target {
+            (amount * self.bonus_bps as i128 / 10_000, 0)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+impl PriceEntry {
+    /// Validates a price against the regulator's ceiling
+    ///
+    /// Prices may not take effect before they are published.
+    pub fn validate(&self, ceiling: i128) -> Result<(), PaymentError> {
+        if self.price_per_unit <= 0 {
+            return Err(PaymentError::InvalidAmount);
+        }
+        if self.effective_from < self.published_at {
+            return Err(PaymentError::InvalidPrice);
+        }
+        if self.price_per_unit > ceiling {
+            return Err(PaymentError::PriceAboveCeiling);
+        }
+        Ok(())
+    }
+
+    /// Adds an entry to a schedule kept in effective-date order,
+    /// replacing any entry that takes effect at the same time
+    ///
+    /// An entry already in force when the new one is published cannot be replaced.
+    pub fn schedule(schedule: &mut Vec<PriceEntry>, entry: PriceEntry) -> Result<(), PaymentError> {
+        let mut index = 0;
+        while index < schedule.len() {
+            let existing = schedule.get(index).unwrap();
+            if existing.effective_from == entry.effective_from {
+                if existing.effective_from <= entry.published_at {
+                    return Err(PaymentError::PriceInForce);
+                }
+                schedule.set(index, entry);
+                return Ok(());
+            }
+            if existing.effective_from > entry.effective_from {
+                break;
+            }
+            index += 1;
+        }
+        schedule.insert(index, entry);
+        Ok(())
+    }
+
+    /// Returns the price in force at `at`, if any
+    pub fn in_force(schedule: &Vec<PriceEntry>, at: u64) -> Option<i128> {
+        schedule
+            .iter()
+            .take_while(|entry| entry.effective_from <= at)
+            .last()
+            .map(|entry| entry.price_per_unit)
+    }
+}
+
+impl BillingLedger {
+    /// Calculates escrowed amounts neither paid out nor refunded
+    pub fn outstanding(&self) -> i128 {
+        self.gross - self.paid - self.refunds
+    }
 }
 
 impl FeeStructure {
@@ -214,4 +570,12 @@ pub enum PaymentError {
     FeesExceedAmount,
     InvalidTransition,
     EscrowNotReleasable,
+    DisputeNotFound,
+    NotArbitrator,
+    InvalidRuling,
+    InvalidMilestones,
+    InvalidShares,
+    InvalidPrice,
+    PriceAboveCeiling,
+    PriceInForce,
 }
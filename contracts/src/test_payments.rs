@@ -1,8 +1,9 @@
 #![cfg(test)]
 
 use crate::payments::{
-    EscrowAccount, FeeStructure, Payment, PaymentError, PaymentStatus, ReleaseConditions,
-    TransactionMetadata,
+    BillingLedger, BonusPolicy, CancellationPolicy, DisputeOutcome, EscrowAccount, EscrowMilestone,
+    FeeStructure, MilestoneTrigger, PayerShare, Payment, PaymentError, PaymentStatus, PriceEntry,
+    ReleaseConditions, TransactionMetadata,
 };
 
 use soroban_sdk::{testutils::Address as _, vec, Address, Env, Symbol};
@@ -155,6 +156,78 @@ fn terminal_states_are_enforced() {
     }
 }
 
+#[test]
+fn disputed_payment_settles_only_by_ruling() {
+    let env = Env::default();
+
+    let mut payment = Payment {
+        id: 1,
+        request_id: 10,
+        payer: Address::generate(&env),
+        payee: Address::generate(&env),
+        amount: 1_000,
+        asset: Address::generate(&env),
+        status: PaymentStatus::Escrowed,
+        escrow_released_at: None,
+    };
+    assert!(payment.can_transition_to(PaymentStatus::Disputed));
+
+    payment.status = PaymentStatus::Disputed;
+    assert!(!payment.is_terminal());
+    assert!(payment.can_transition_to(PaymentStatus::Completed));
+    assert!(payment.can_transition_to(PaymentStatus::Refunded));
+    assert!(!payment.can_transition_to(PaymentStatus::Escrowed));
+    assert!(!payment.can_transition_to(PaymentStatus::Cancelled));
+}
+
+// ======================================================
+// PayerShare Tests
+// ======================================================
+
+#[test]
+fn payer_shares_must_be_distinct_and_positive() {
+    let env = Env::default();
+    let payee = Address::generate(&env);
+    let insurer = Address::generate(&env);
+    let share = |payer: &Address, amount| PayerShare {
+        payer: payer.clone(),
+        amount,
+        funded: false,
+    };
+
+    let valid = vec![
+        &env,
+        share(&insurer, 700),
+        share(&Address::generate(&env), 300),
+    ];
+    assert!(PayerShare::validate_shares(&valid, &payee).is_ok());
+
+    for invalid in [
+        vec![&env],
+        vec![&env, share(&insurer, 0)],
+        vec![&env, share(&payee, 100)],
+        vec![&env, share(&insurer, 100), share(&insurer, 200)],
+    ] {
+        assert_eq!(
+            PayerShare::validate_shares(&invalid, &payee),
+            Err(PaymentError::InvalidShares)
+        );
+    }
+}
+
+#[test]
+fn refunds_are_apportioned_to_funded_shares() {
+    let env = Env::default();
+    let share = |amount, funded| PayerShare {
+        payer: Address::generate(&env),
+        amount,
+        funded,
+    };
+
+    let shares = vec![&env, share(2, true), share(1, true), share(5, false)];
+    assert_eq!(PayerShare::apportion(&shares, 100), vec![&env, 66, 34, 0]);
+}
+
 // ======================================================
 // EscrowAccount Tests
 // ======================================================
@@ -193,6 +266,65 @@ fn escrow_fails_release_without_conditions() {
     assert!(!escrow.can_release(200, None));
 }
 
+#[test]
+fn escrow_settles_dispute_outcomes() {
+    let escrow = EscrowAccount {
+        payment_id: 1,
+        locked_amount: 1_000,
+        release_conditions: ReleaseConditions {
+            medical_records_verified: true,
+            min_timestamp: 0,
+            authorized_approver: None,
+        },
+    };
+
+    assert_eq!(escrow.settle(&DisputeOutcome::Release), Ok((1_000, 0)));
+    assert_eq!(escrow.settle(&DisputeOutcome::Refund), Ok((0, 1_000)));
+    assert_eq!(escrow.settle(&DisputeOutcome::Split(600)), Ok((600, 400)));
+    assert_eq!(
+        escrow.settle(&DisputeOutcome::Split(1_000)),
+        Err(PaymentError::InvalidRuling)
+    );
+    assert_eq!(
+        escrow.settle(&DisputeOutcome::Split(0)),
+        Err(PaymentError::InvalidRuling)
+    );
+}
+
+#[test]
+fn milestone_schedule_must_not_exceed_whole_amount() {
+    let env = Env::default();
+    let milestone = |trigger, share_bps| EscrowMilestone {
+        trigger,
+        share_bps,
+        released_at: None,
+    };
+
+    let schedule = vec![
+        &env,
+        milestone(MilestoneTrigger::UnitInTransit(1), 2_000),
+        milestone(MilestoneTrigger::UnitDelivered(1), 8_000),
+    ];
+    assert!(EscrowMilestone::validate_schedule(&schedule).is_ok());
+    assert_eq!(schedule.get_unchecked(0).share_of(1_000), 200);
+
+    let over = vec![
+        &env,
+        milestone(MilestoneTrigger::UnitInTransit(1), 6_000),
+        milestone(MilestoneTrigger::RequestFulfilled(1), 6_000),
+    ];
+    assert_eq!(
+        EscrowMilestone::validate_schedule(&over),
+        Err(PaymentError::InvalidMilestones)
+    );
+
+    let zero = vec![&env, milestone(MilestoneTrigger::UnitDelivered(1), 0)];
+    assert_eq!(
+        EscrowMilestone::validate_schedule(&zero),
+        Err(PaymentError::InvalidMilestones)
+    );
+}
+
 // ======================================================
 // FeeStructure Tests
 // ======================================================
@@ -223,6 +355,100 @@ fn fees_cannot_exceed_payment_amount() {
     );
 }
 
+#[test]
+fn cancellation_fee_depends_on_service_stage() {
+    let policy = CancellationPolicy {
+        pending_fee_bps: 0,
+        approved_fee_bps: 1_500,
+    };
+
+    assert!(policy.validate().is_ok());
+    assert_eq!(policy.split_refund(1_000, false), (0, 1_000));
+    assert_eq!(policy.split_refund(1_000, true), (150, 850));
+
+    let invalid = CancellationPolicy {
+        pending_fee_bps: 10_001,
+        approved_fee_bps: 0,
+    };
+    assert_eq!(invalid.validate(), Err(PaymentError::InvalidFee));
+}
+
+#[test]
+fn billing_ledger_outstanding_excludes_settled_amounts() {
+    let ledger = BillingLedger {
+        units_delivered: 3,
+        gross: 1_500,
+        fees: 30,
+        paid: 900,
+        refunds: 200,
+    };
+
+    assert_eq!(ledger.outstanding(), 400);
+}
+
+#[test]
+fn bonus_policy_rewards_early_and_penalises_late_delivery() {
+    let policy = BonusPolicy {
+        target_window_bps: 5_000,
+        bonus_bps: 500,
+        penalty_bps: 1_000,
+    };
+    assert!(policy.validate().is_ok());
+
+    // Due 1000s after the request; the target window is the first 500s
+    assert_eq!(policy.adjustment(2_000, 100, 1_100, 600), (100, 0));
+    assert_eq!(policy.adjustment(2_000, 100, 1_100, 1_100), (0, 0));
+    assert_eq!(policy.adjustment(2_000, 100, 1_100, 1_101), (0, 200));
+
+    let invalid = BonusPolicy {
+        target_window_bps: 10_001,
+        ..policy
+    };
+    assert_eq!(invalid.validate(), Err(PaymentError::InvalidFee));
+}
+
+#[test]
+fn price_schedule_applies_latest_effective_entry() {
+    let env = Env::default();
+    let entry = |price_per_unit, effective_from| PriceEntry {
+        price_per_unit,
+        effective_from,
+        published_at: 0,
+    };
+
+    let mut schedule = vec![&env];
+    assert!(PriceEntry::schedule(&mut schedule, entry(150, 200)).is_ok());
+    assert!(PriceEntry::schedule(&mut schedule, entry(100, 100)).is_ok());
+    assert!(PriceEntry::schedule(&mut schedule, entry(120, 200)).is_ok());
+    assert_eq!(schedule, vec![&env, entry(100, 100), entry(120, 200)]);
+
+    // Once in force, an entry can no longer be replaced
+    let late = PriceEntry {
+        published_at: 200,
+        ..entry(130, 200)
+    };
+    assert_eq!(
+        PriceEntry::schedule(&mut schedule, late),
+        Err(PaymentError::PriceInForce)
+    );
+    assert_eq!(schedule, vec![&env, entry(100, 100), entry(120, 200)]);
+
+    assert_eq!(PriceEntry::in_force(&schedule, 99), None);
+    assert_eq!(PriceEntry::in_force(&schedule, 199), Some(100));
+    assert_eq!(PriceEntry::in_force(&schedule, 200), Some(120));
+
+    assert!(entry(100, 100).validate(100).is_ok());
+    assert_eq!(
+        entry(101, 100).validate(100),
+        Err(PaymentError::PriceAboveCeiling)
+    );
+    let backdated = PriceEntry {
+        published_at: 101,
+        ..entry(100, 100)
+    };
+    assert_eq!(backdated.validate(100), Err(PaymentError::InvalidPrice));
+}
+
 // ======================================================
 // Transaction Metadata Tests
 // ======================================================
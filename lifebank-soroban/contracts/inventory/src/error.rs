@@ -25,6 +25,7 @@ pub enum ContractError {
     Expired = 22,
     BloodUnitExpired = 23,
     DuplicateBloodUnit = 24,
+    ContractPaused = 25,
 
     // Permission errors (30-39)
     InsufficientBalance = 30,
@@ -1,6 +1,62 @@
-use crate::types::{BloodRegisteredEvent, BloodType, StatusChangeEvent};
+use crate::types::{
+    BloodRegisteredEvent, BloodType, EntityId, EventEnvelope, EventPayload, GuardianSetEvent,
+    IndexesRepairedEvent, InitializedEvent, PauseChangedEvent, PauseScope, StatusChangeEvent,
+    StockAlertEvent, ThresholdSetEvent, EVENT_SCHEMA_VERSION,
+};
 use soroban_sdk::{Address, Env, String, Symbol};
 
+/// Publish a payload wrapped in the versioned event envelope
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `topic` - Event topic
+/// * `entity_type` - Kind of entity the event is about
+/// * `entity_id` - Id of that entity
+/// * `actor` - Address that triggered the change, if any
+/// * `payload` - Typed event data
+fn publish(
+    env: &Env,
+    topic: Symbol,
+    entity_type: &str,
+    entity_id: EntityId,
+    actor: Option<Address>,
+    payload: EventPayload,
+) {
+    let envelope = EventEnvelope {
+        version: EVENT_SCHEMA_VERSION,
+        entity_type: Symbol::new(env, entity_type),
+        entity_id,
+        actor,
+        timestamp: env.ledger().timestamp(),
+        payload,
+    };
+
+    // The envelope is a contracttype rather than a #[contractevent] so decoders
+    // can read every event through one schema
+    #[allow(deprecated)]
+    env.events().publish((topic,), envelope);
+}
+
+/// Emit an Initialized event
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin address set at initialization
+pub fn emit_initialized(env: &Env, admin: &Address) {
+    let event = InitializedEvent {
+        admin: admin.clone(),
+    };
+
+    publish(
+        env,
+        Symbol::new(env, "initialized"),
+        "admin",
+        EntityId::Account(admin.clone()),
+        Some(admin.clone()),
+        EventPayload::Initialized(event),
+    );
+}
+
 /// Emit a BloodRegistered event
 ///
 /// # Arguments
@@ -29,10 +85,120 @@ pub fn emit_blood_registered(
         registered_at,
     };
 
-    env.events()
-        .publish((Symbol::new(env, "blood_registered"),), event);
+    publish(
+        env,
+        Symbol::new(env, "blood_registered"),
+        "unit",
+        EntityId::Record(blood_unit_id),
+        Some(bank_id.clone()),
+        EventPayload::BloodRegistered(event),
+    );
+}
+
+/// Emit a shortage event when a bank's available stock drops below its threshold
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `bank_id` - Blood bank whose stock dropped
+/// * `blood_type` - Type of blood
+/// * `available_units` - Available units after the change
+/// * `threshold_units` - Configured minimum number of available units
+pub fn emit_stock_shortage(
+    env: &Env,
+    bank_id: &Address,
+    blood_type: BloodType,
+    available_units: u32,
+    threshold_units: u32,
+) {
+    emit_stock_alert(
+        env,
+        Symbol::new(env, "shortage"),
+        bank_id,
+        blood_type,
+        available_units,
+        threshold_units,
+    );
 }
 
+/// Emit a recovered event when a bank's available stock is back at its threshold
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `bank_id` - Blood bank whose stock recovered
+/// * `blood_type` - Type of blood
+/// * `available_units` - Available units after the change
+/// * `threshold_units` - Configured minimum number of available units
+pub fn emit_stock_recovered(
+    env: &Env,
+    bank_id: &Address,
+    blood_type: BloodType,
+    available_units: u32,
+    threshold_units: u32,
+) {
+    emit_stock_alert(
+        env,
+        Symbol::new(env, "recovered"),
+        bank_id,
+        blood_type,
+        available_units,
+        threshold_units,
+    );
+}
+
+fn emit_stock_alert(
+    env: &Env,
+    topic: Symbol,
+    bank_id: &Address,
+    blood_type: BloodType,
+    available_units: u32,
+    threshold_units: u32,
+) {
+    let event = StockAlertEvent {
+        bank_id: bank_id.clone(),
+        blood_type,
+        available_units,
+        threshold_units,
+        timestamp: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        topic,
+        "bank",
+        EntityId::Account(bank_id.clone()),
+        None,
+        EventPayload::StockAlert(event),
+    );
+}
+
+/// Emit a ThresholdSet event when a bank configures its low-stock threshold
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `bank_id` - Blood bank that set the threshold
+/// * `blood_type` - Type of blood
+/// * `threshold_units` - New minimum number of available units
+pub fn emit_threshold_set(
+    env: &Env,
+    bank_id: &Address,
+    blood_type: BloodType,
+    threshold_units: u32,
+) {
+    let event = ThresholdSetEvent {
+        bank_id: bank_id.clone(),
+        blood_type,
+        threshold_units,
+    };
+
+    publish(
+        env,
+        Symbol::new(env, "threshold_set"),
+        "bank",
+        EntityId::Account(bank_id.clone()),
+        Some(bank_id.clone()),
+        EventPayload::ThresholdSet(event),
+    );
+}
 
 pub fn emit_status_change(
     env: &Env,
@@ -53,6 +219,96 @@ pub fn emit_status_change(
         reason,
     };
 
-    env.events()
-        .publish((Symbol::new(env, "status_changed"),), event);
+    publish(
+        env,
+        Symbol::new(env, "status_changed"),
+        "unit",
+        EntityId::Record(blood_unit_id),
+        Some(authorized_by.clone()),
+        EventPayload::StatusChange(event),
+    );
+}
+
+/// Emit an IndexesRepaired event after the admin fixes index drift
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin who ran the repair
+/// * `from_id` - First unit ID of the repaired page
+/// * `to_id` - Last unit ID of the repaired page
+/// * `issues_fixed` - Number of issues fixed
+pub fn emit_indexes_repaired(
+    env: &Env,
+    admin: &Address,
+    from_id: u64,
+    to_id: u64,
+    issues_fixed: u32,
+) {
+    let event = IndexesRepairedEvent {
+        from_id,
+        to_id,
+        issues_fixed,
+    };
+
+    publish(
+        env,
+        Symbol::new(env, "indexes_repaired"),
+        "admin",
+        EntityId::Account(admin.clone()),
+        Some(admin.clone()),
+        EventPayload::IndexesRepaired(event),
+    );
+}
+
+/// Emit a GuardianSet event when the admin appoints a guardian
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin who appointed the guardian
+/// * `guardian` - New guardian address
+pub fn emit_guardian_set(env: &Env, admin: &Address, guardian: &Address) {
+    let event = GuardianSetEvent {
+        guardian: guardian.clone(),
+    };
+
+    publish(
+        env,
+        Symbol::new(env, "guardian_set"),
+        "admin",
+        EntityId::Account(guardian.clone()),
+        Some(admin.clone()),
+        EventPayload::GuardianSet(event),
+    );
+}
+
+/// Emit a PauseChanged event when an operation family is paused or unpaused
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `caller` - Admin or guardian who made the change
+/// * `scope` - Operation family the change applies to
+/// * `paused` - Whether the family is now paused
+/// * `reason` - Why the pause was raised or lifted
+pub fn emit_pause_changed(
+    env: &Env,
+    caller: &Address,
+    scope: PauseScope,
+    paused: bool,
+    reason: String,
+) {
+    let topic = if paused { "paused" } else { "unpaused" };
+    let event = PauseChangedEvent {
+        scope,
+        paused,
+        reason,
+    };
+
+    publish(
+        env,
+        Symbol::new(env, topic),
+        "contract",
+        EntityId::Account(env.current_contract_address()),
+        Some(caller.clone()),
+        EventPayload::PauseChanged(event),
+    );
 }
@@ -0,0 +1,333 @@
+use crate::storage;
+use crate::types::{
+    BloodStatus, BloodType, DataKey, IndexIssue, IndexKind, InvariantReport, IssueKind,
+};
+use soroban_sdk::{Address, Env, Map, Vec};
+
+const BLOOD_TYPES: [BloodType; 8] = [
+    BloodType::APositive,
+    BloodType::ANegative,
+    BloodType::BPositive,
+    BloodType::BNegative,
+    BloodType::ABPositive,
+    BloodType::ABNegative,
+    BloodType::OPositive,
+    BloodType::ONegative,
+];
+
+const STATUSES: [BloodStatus; 5] = [
+    BloodStatus::Available,
+    BloodStatus::Reserved,
+    BloodStatus::InTransit,
+    BloodStatus::Delivered,
+    BloodStatus::Expired,
+];
+
+/// Issues found on one page
+struct Checker<'a> {
+    env: &'a Env,
+    issues: Vec<IndexIssue>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, index: IndexKind, kind: IssueKind, unit_id: u64) {
+        self.report_counter(index, kind, unit_id, 0, 0);
+    }
+
+    fn report_counter(
+        &mut self,
+        index: IndexKind,
+        kind: IssueKind,
+        unit_id: u64,
+        expected: u64,
+        actual: u64,
+    ) {
+        self.issues.push_back(IndexIssue {
+            index,
+            kind,
+            unit_id,
+            expected,
+            actual,
+        });
+    }
+}
+
+/// In-memory copy of one index, normalised to ascending order
+///
+/// Changes are only written back when repairing.
+struct Index {
+    kind: IndexKind,
+    key: DataKey,
+    ids: Vec<u64>,
+    dirty: bool,
+}
+
+impl Index {
+    fn load(checker: &mut Checker, kind: IndexKind, key: DataKey) -> Index {
+        let stored = storage::get_index(checker.env, &key);
+
+        let mut sorted = true;
+        for i in 1..stored.len() {
+            if stored.get_unchecked(i - 1) >= stored.get_unchecked(i) {
+                sorted = false;
+                break;
+            }
+        }
+
+        let ids = if sorted {
+            stored
+        } else {
+            checker.report(kind, IssueKind::Unsorted, 0);
+            let mut ids = Vec::new(checker.env);
+            for id in stored.iter() {
+                storage::insert_sorted(&mut ids, id);
+            }
+            ids
+        };
+
+        Index {
+            kind,
+            key,
+            ids,
+            dirty: !sorted,
+        }
+    }
+
+    fn contains(&self, id: u64) -> bool {
+        self.ids.binary_search(id).is_ok()
+    }
+
+    /// Check that `id` is listed exactly when it `belongs` here
+    fn expect(&mut self, checker: &mut Checker, id: u64, belongs: bool) {
+        match (belongs, self.ids.binary_search(id)) {
+            (true, Err(pos)) => {
+                checker.report(self.kind, IssueKind::MissingEntry, id);
+                self.ids.insert(pos, id);
+                self.dirty = true;
+            }
+            (false, Ok(pos)) => {
+                checker.report(self.kind, IssueKind::StaleEntry, id);
+                self.ids.remove(pos);
+                self.dirty = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Drop entries for an ID that has no record
+    fn drop_dangling(&mut self, checker: &mut Checker, id: u64) {
+        if let Ok(pos) = self.ids.binary_search(id) {
+            checker.report(self.kind, IssueKind::DanglingEntry, id);
+            self.ids.remove(pos);
+            self.dirty = true;
+        }
+    }
+
+    /// Drop entries above the last issued unit ID
+    fn drop_above(&mut self, checker: &mut Checker, last_id: u64) {
+        while let Some(id) = self.ids.last() {
+            if id <= last_id {
+                break;
+            }
+            checker.report(self.kind, IssueKind::DanglingEntry, id);
+            self.ids.pop_back();
+            self.dirty = true;
+        }
+    }
+
+    fn save(&self, env: &Env) {
+        if self.dirty {
+            storage::set_index(env, &self.key, &self.ids);
+        }
+    }
+}
+
+/// Bank and donor indexes, loaded on first use and keyed by storage key
+struct AccountIndexes {
+    ids: Map<DataKey, Vec<u64>>,
+    dirty: Map<DataKey, bool>,
+}
+
+impl AccountIndexes {
+    /// Check that the index under `key` lists `id`
+    fn expect(&mut self, checker: &mut Checker, kind: IndexKind, key: DataKey, id: u64) {
+        let mut ids = match self.ids.get(key.clone()) {
+            Some(ids) => ids,
+            None => {
+                let index = Index::load(checker, kind, key.clone());
+                if index.dirty {
+                    self.dirty.set(key.clone(), true);
+                }
+                index.ids
+            }
+        };
+
+        if let Err(pos) = ids.binary_search(id) {
+            checker.report(kind, IssueKind::MissingEntry, id);
+            ids.insert(pos, id);
+            self.dirty.set(key.clone(), true);
+        }
+        self.ids.set(key, ids);
+    }
+
+    fn contains(&self, key: DataKey, id: u64) -> bool {
+        self.ids
+            .get(key)
+            .is_some_and(|ids| ids.binary_search(id).is_ok())
+    }
+
+    fn save(&self, env: &Env) {
+        for key in self.dirty.keys().iter() {
+            if let Some(ids) = self.ids.get(key.clone()) {
+                storage::set_index(env, &key, &ids);
+            }
+        }
+    }
+}
+
+/// Check one page of blood units, fixing what is found when `repair` is set
+///
+/// Each unit must be listed in the blood type, status, bank and donor index
+/// matching its record and in no other blood type or status index. Per-bank
+/// `AvailableStock` counters touched by the page are compared with the
+/// Available entries of the (already fixed) indexes.
+pub fn check(env: &Env, cursor: u64, limit: u32, repair: bool) -> InvariantReport {
+    let mut checker = Checker {
+        env,
+        issues: Vec::new(env),
+    };
+
+    let mut type_indexes = BLOOD_TYPES.map(|blood_type| {
+        Index::load(
+            &mut checker,
+            IndexKind::BloodType,
+            DataKey::BloodTypeIndex(blood_type),
+        )
+    });
+    let mut status_indexes = STATUSES.map(|status| {
+        Index::load(
+            &mut checker,
+            IndexKind::Status,
+            DataKey::StatusIndex(status),
+        )
+    });
+    let mut account_indexes = AccountIndexes {
+        ids: Map::new(env),
+        dirty: Map::new(env),
+    };
+    let mut stock_keys: Map<(Address, BloodType), u64> = Map::new(env);
+
+    let counter = storage::get_blood_unit_counter(env);
+    let mut unit_id = cursor.max(1);
+    let mut scanned = 0u32;
+
+    while unit_id <= counter && scanned < limit {
+        match storage::get_blood_unit(env, unit_id) {
+            None => {
+                checker.report(IndexKind::Record, IssueKind::MissingRecord, unit_id);
+                for index in type_indexes.iter_mut().chain(status_indexes.iter_mut()) {
+                    index.drop_dangling(&mut checker, unit_id);
+                }
+            }
+            Some(unit) => {
+                for (blood_type, index) in BLOOD_TYPES.iter().zip(type_indexes.iter_mut()) {
+                    index.expect(&mut checker, unit_id, *blood_type == unit.blood_type);
+                }
+                for (status, index) in STATUSES.iter().zip(status_indexes.iter_mut()) {
+                    index.expect(&mut checker, unit_id, *status == unit.status);
+                }
+
+                account_indexes.expect(
+                    &mut checker,
+                    IndexKind::Bank,
+                    DataKey::BankIndex(unit.bank_id.clone()),
+                    unit_id,
+                );
+                if let Some(donor_id) = unit.donor_id.clone() {
+                    account_indexes.expect(
+                        &mut checker,
+                        IndexKind::Donor,
+                        DataKey::DonorIndex(donor_id),
+                        unit_id,
+                    );
+                }
+
+                let stock_key = (unit.bank_id, unit.blood_type);
+                if !stock_keys.contains_key(stock_key.clone()) {
+                    stock_keys.set(stock_key, unit_id);
+                }
+            }
+        }
+
+        unit_id += 1;
+        scanned += 1;
+    }
+
+    let done = unit_id > counter;
+    if done {
+        // Units registered past a lagging counter would be overwritten
+        let mut last_id = counter;
+        while storage::get_blood_unit(env, last_id + 1).is_some() {
+            last_id += 1;
+        }
+        if last_id != counter {
+            checker.report_counter(
+                IndexKind::UnitCounter,
+                IssueKind::CounterMismatch,
+                0,
+                last_id,
+                counter,
+            );
+            if repair {
+                storage::set_blood_unit_counter(env, last_id);
+            }
+        }
+
+        for index in type_indexes.iter_mut().chain(status_indexes.iter_mut()) {
+            index.drop_above(&mut checker, last_id);
+        }
+    }
+
+    let available = &status_indexes[0];
+    for ((bank_id, blood_type), first_unit) in stock_keys.iter() {
+        let type_index = BLOOD_TYPES
+            .iter()
+            .position(|candidate| *candidate == blood_type)
+            .map(|position| &type_indexes[position]);
+        let bank_key = DataKey::BankIndex(bank_id.clone());
+
+        let mut expected = 0u32;
+        for id in available.ids.iter() {
+            let in_type = type_index.is_some_and(|index| index.contains(id));
+            if in_type && account_indexes.contains(bank_key.clone(), id) {
+                expected += 1;
+            }
+        }
+
+        let actual = storage::get_available_stock(env, &bank_id, blood_type);
+        if actual != expected {
+            checker.report_counter(
+                IndexKind::AvailableStock,
+                IssueKind::CounterMismatch,
+                first_unit,
+                expected as u64,
+                actual as u64,
+            );
+            if repair {
+                storage::set_available_stock(env, &bank_id, blood_type, expected);
+            }
+        }
+    }
+
+    if repair {
+        for index in type_indexes.iter().chain(status_indexes.iter()) {
+            index.save(env);
+        }
+        account_indexes.save(env);
+    }
+
+    InvariantReport {
+        issues: checker.issues,
+        next_cursor: if done { 0 } else { unit_id },
+    }
+}
@@ -2,14 +2,26 @@
 
 mod error;
 mod events;
+mod invariants;
 mod storage;
 mod types;
 mod validation;
 
-use crate::error::ContractError;
-use crate::types::{BloodStatus, BloodType, BloodUnit, DataKey};
+pub use crate::error::ContractError;
+pub use crate::types::{
+    BloodRegisteredEvent, BloodStatus, BloodType, BloodUnit, EntityId, EventEnvelope, EventPayload,
+    GuardianSetEvent, IndexIssue, IndexKind, IndexesRepairedEvent, InitializedEvent,
+    InvariantReport, IssueKind, PauseChangedEvent, PauseScope, StatusChangeEvent, StockAlertEvent,
+    ThresholdSetEvent, EVENT_SCHEMA_VERSION,
+};
+
+use crate::types::DataKey;
 
 use soroban_sdk::{contract, contractimpl, Address, Env, Map, String, Vec};
+
+/// Maximum number of units one `verify_invariants` or `repair_indexes` page scans
+const MAX_INVARIANT_SCAN: u32 = 100;
+
 #[contract]
 pub struct InventoryContract;
 
@@ -34,6 +46,8 @@ impl InventoryContract {
         // Set admin
         storage::set_admin(&env, &admin);
 
+        events::emit_initialized(&env, &admin);
+
         Ok(())
     }
 
@@ -55,6 +69,7 @@ impl InventoryContract {
     /// - `NotAuthorizedBloodBank`: Bank is not authorized
     /// - `InvalidQuantity`: Quantity outside acceptable range
     /// - `InvalidExpiration`: Expiration date is invalid
+    /// - `ContractPaused`: Registration is paused
     ///
     /// # Events
     /// Emits `BloodRegistered` event with all blood unit details
@@ -79,6 +94,8 @@ impl InventoryContract {
             return Err(ContractError::NotAuthorizedBloodBank);
         }
 
+        Self::require_not_paused(&env, PauseScope::Registration)?;
+
         // 4. Validate input parameters
         validation::validate_blood_registration(&env, quantity_ml, expiration_timestamp)?;
         validation::validate_minimum_shelf_life(&env, expiration_timestamp)?;
@@ -111,6 +128,7 @@ impl InventoryContract {
         storage::add_to_bank_index(&env, &blood_unit);
         storage::add_to_status_index(&env, &blood_unit);
         storage::add_to_donor_index(&env, &blood_unit);
+        Self::track_available_stock(&env, &blood_unit, None, BloodStatus::Available);
 
         // 10. Emit event
         events::emit_blood_registered(
@@ -155,12 +173,14 @@ impl InventoryContract {
             return Err(ContractError::Unauthorized);
         }
 
+        Self::require_status_change_allowed(&env, new_status)?;
+
         let mut blood_unit =
             storage::get_blood_unit(&env, unit_id).ok_or(ContractError::NotFound)?;
 
         let current_time = env.ledger().timestamp();
 
-        if blood_unit.is_expired(current_time) {
+        if blood_unit.is_expired(current_time) && new_status != BloodStatus::Expired {
             return Err(ContractError::BloodUnitExpired);
         }
 
@@ -173,6 +193,8 @@ impl InventoryContract {
         let old_status = blood_unit.status;
         blood_unit.status = new_status;
         storage::set_blood_unit(&env, &blood_unit);
+        storage::update_status_index(&env, unit_id, old_status, new_status);
+        Self::track_available_stock(&env, &blood_unit, Some(old_status), new_status);
 
         storage::record_status_change(
             &env,
@@ -239,6 +261,8 @@ impl InventoryContract {
             return Err(ContractError::Unauthorized);
         }
 
+        Self::require_status_change_allowed(&env, new_status)?;
+
         let current_time = env.ledger().timestamp();
         let mut updated_count = 0u64;
 
@@ -247,7 +271,7 @@ impl InventoryContract {
             let mut blood_unit =
                 storage::get_blood_unit(&env, unit_id).ok_or(ContractError::NotFound)?;
 
-            if blood_unit.is_expired(current_time) {
+            if blood_unit.is_expired(current_time) && new_status != BloodStatus::Expired {
                 return Err(ContractError::BloodUnitExpired);
             }
 
@@ -260,6 +284,8 @@ impl InventoryContract {
             let old_status = blood_unit.status;
             blood_unit.status = new_status;
             storage::set_blood_unit(&env, &blood_unit);
+            storage::update_status_index(&env, unit_id, old_status, new_status);
+            Self::track_available_stock(&env, &blood_unit, Some(old_status), new_status);
 
             storage::record_status_change(
                 &env,
@@ -292,6 +318,327 @@ impl InventoryContract {
     pub fn get_status_change_count(env: Env, unit_id: u64) -> u64 {
         storage::get_blood_unit_status_change_count(&env, unit_id)
     }
+
+    /// Set the minimum number of Available units a bank wants to keep for a blood type
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `bank_id` - Blood bank's address (must be authorized)
+    /// * `blood_type` - Type of blood the threshold applies to
+    /// * `min_units` - Minimum Available units (0 disables alerts)
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `NotAuthorizedBloodBank`: Bank is not authorized
+    ///
+    /// # Events
+    /// Emits `threshold_set`, `shortage` when the bank's stock is already below
+    /// the new threshold, and `recovered` when lowering the threshold ends a shortage
+    pub fn set_stock_threshold(
+        env: Env,
+        bank_id: Address,
+        blood_type: BloodType,
+        min_units: u32,
+    ) -> Result<(), ContractError> {
+        bank_id.require_auth();
+
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if !storage::is_authorized_bank(&env, &bank_id) {
+            return Err(ContractError::NotAuthorizedBloodBank);
+        }
+
+        let old_threshold = storage::get_stock_threshold(&env, &bank_id, blood_type);
+        storage::set_stock_threshold(&env, &bank_id, blood_type, min_units);
+        events::emit_threshold_set(&env, &bank_id, blood_type, min_units);
+
+        let available = storage::get_available_stock(&env, &bank_id, blood_type);
+        if available < min_units && available >= old_threshold {
+            events::emit_stock_shortage(&env, &bank_id, blood_type, available, min_units);
+        } else if min_units > 0 && available >= min_units && available < old_threshold {
+            events::emit_stock_recovered(&env, &bank_id, blood_type, available, min_units);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_stock_threshold(env: Env, bank_id: Address, blood_type: BloodType) -> u32 {
+        storage::get_stock_threshold(&env, &bank_id, blood_type)
+    }
+
+    pub fn get_available_stock(env: Env, bank_id: Address, blood_type: BloodType) -> u32 {
+        storage::get_available_stock(&env, &bank_id, blood_type)
+    }
+
+    /// Appoint the guardian, who may pause operations alongside the admin
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `guardian` - Guardian address, replacing any previous one
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    ///
+    /// # Events
+    /// Emits `guardian_set`
+    pub fn set_guardian(env: Env, guardian: Address) -> Result<(), ContractError> {
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let admin = storage::get_admin(&env);
+        admin.require_auth();
+
+        storage::set_guardian(&env, &guardian);
+        events::emit_guardian_set(&env, &admin, &guardian);
+
+        Ok(())
+    }
+
+    pub fn get_guardian(env: Env) -> Option<Address> {
+        storage::get_guardian(&env)
+    }
+
+    /// Pause an operation family, or every family with `PauseScope::All`
+    ///
+    /// Paused operations fail with `ContractPaused`; reads and expiring
+    /// units keep working.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `caller` - Admin or guardian address
+    /// * `scope` - Operation family to pause
+    /// * `reason` - Why the pause is raised
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `Unauthorized`: Caller is neither the admin nor the guardian
+    ///
+    /// # Events
+    /// Emits `paused`
+    pub fn pause(
+        env: Env,
+        caller: Address,
+        scope: PauseScope,
+        reason: String,
+    ) -> Result<(), ContractError> {
+        caller.require_auth();
+
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let is_guardian = storage::get_guardian(&env).is_some_and(|guardian| guardian == caller);
+        if caller != storage::get_admin(&env) && !is_guardian {
+            return Err(ContractError::Unauthorized);
+        }
+
+        storage::set_paused(&env, scope, true);
+        events::emit_pause_changed(&env, &caller, scope, true, reason);
+
+        Ok(())
+    }
+
+    /// Lift the pause on an operation family
+    ///
+    /// Only the admin can unpause, so a compromised guardian key cannot undo
+    /// a pause. Unpausing `PauseScope::All` leaves families paused on their
+    /// own still paused.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `caller` - Admin address
+    /// * `scope` - Operation family to unpause
+    /// * `reason` - Why the pause is lifted
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `Unauthorized`: Caller is not the admin
+    ///
+    /// # Events
+    /// Emits `unpaused`
+    pub fn unpause(
+        env: Env,
+        caller: Address,
+        scope: PauseScope,
+        reason: String,
+    ) -> Result<(), ContractError> {
+        caller.require_auth();
+
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if caller != storage::get_admin(&env) {
+            return Err(ContractError::Unauthorized);
+        }
+
+        storage::set_paused(&env, scope, false);
+        events::emit_pause_changed(&env, &caller, scope, false, reason);
+
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env, scope: PauseScope) -> bool {
+        storage::is_paused(&env, scope)
+    }
+
+    /// Check one page of blood units against their indexes and counters
+    ///
+    /// Read-only. Scans up to `limit` unit IDs from `cursor` and reports units
+    /// missing from an index they belong in, index entries under the wrong key
+    /// or without a record, unsorted indexes, and `AvailableStock` counters that
+    /// disagree with the indexes. Entries beyond the unit counter and a counter
+    /// lagging behind the records are checked on the last page.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First unit ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of units to scan (1-100)
+    ///
+    /// # Returns
+    /// Issues found and the cursor to resume from (0 once all units are checked)
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    pub fn verify_invariants(
+        env: Env,
+        cursor: u64,
+        limit: u32,
+    ) -> Result<InvariantReport, ContractError> {
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if limit == 0 || limit > MAX_INVARIANT_SCAN {
+            return Err(ContractError::InvalidInput);
+        }
+
+        Ok(invariants::check(&env, cursor, limit, false))
+    }
+
+    /// Fix what `verify_invariants` reports for one page of blood units
+    ///
+    /// Missing entries are added, stale and dangling entries removed, indexes
+    /// re-sorted and counters reset. Stock counters are recomputed from the
+    /// indexes after the page's own fixes, so run a full pass and then verify.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `authorized_by` - Admin address
+    /// * `cursor` - First unit ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of units to scan (1-100)
+    ///
+    /// # Returns
+    /// Issues fixed and the cursor to resume from (0 once all units are checked)
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `Unauthorized`: Caller is not the admin
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    ///
+    /// # Events
+    /// Emits `indexes_repaired` when anything was fixed
+    pub fn repair_indexes(
+        env: Env,
+        authorized_by: Address,
+        cursor: u64,
+        limit: u32,
+    ) -> Result<InvariantReport, ContractError> {
+        authorized_by.require_auth();
+
+        if !env.storage().instance().has(&DataKey::Admin) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if authorized_by != storage::get_admin(&env) {
+            return Err(ContractError::Unauthorized);
+        }
+
+        if limit == 0 || limit > MAX_INVARIANT_SCAN {
+            return Err(ContractError::InvalidInput);
+        }
+
+        let report = invariants::check(&env, cursor, limit, true);
+        if !report.issues.is_empty() {
+            let from_id = cursor.max(1);
+            let to_id = if report.next_cursor == 0 {
+                storage::get_blood_unit_counter(&env)
+            } else {
+                report.next_cursor - 1
+            };
+            events::emit_indexes_repaired(
+                &env,
+                &authorized_by,
+                from_id,
+                to_id,
+                report.issues.len(),
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Fail with `ContractPaused` if an operation family is paused
+    fn require_not_paused(env: &Env, scope: PauseScope) -> Result<(), ContractError> {
+        if storage::is_paused(env, scope) {
+            return Err(ContractError::ContractPaused);
+        }
+        Ok(())
+    }
+
+    /// Fail with `ContractPaused` if the family a status change belongs to is paused
+    ///
+    /// Expiry is never paused so stale units can always be taken out of stock.
+    fn require_status_change_allowed(
+        env: &Env,
+        new_status: BloodStatus,
+    ) -> Result<(), ContractError> {
+        let scope = match new_status {
+            BloodStatus::Available | BloodStatus::Reserved => PauseScope::Allocation,
+            BloodStatus::InTransit | BloodStatus::Delivered => PauseScope::Transfers,
+            BloodStatus::Expired => return Ok(()),
+        };
+        Self::require_not_paused(env, scope)
+    }
+
+    /// Keep the bank's Available counter in sync and emit threshold alerts
+    fn track_available_stock(
+        env: &Env,
+        blood_unit: &BloodUnit,
+        old_status: Option<BloodStatus>,
+        new_status: BloodStatus,
+    ) {
+        let was_available = old_status == Some(BloodStatus::Available);
+        let is_available = new_status == BloodStatus::Available;
+        if was_available == is_available {
+            return;
+        }
+
+        let bank_id = &blood_unit.bank_id;
+        let blood_type = blood_unit.blood_type;
+        let before = storage::get_available_stock(env, bank_id, blood_type);
+        let after = if is_available {
+            before.saturating_add(1)
+        } else {
+            before.saturating_sub(1)
+        };
+        storage::set_available_stock(env, bank_id, blood_type, after);
+
+        let threshold = storage::get_stock_threshold(env, bank_id, blood_type);
+        if threshold == 0 {
+            return;
+        }
+
+        if before >= threshold && after < threshold {
+            events::emit_stock_shortage(env, bank_id, blood_type, after, threshold);
+        } else if before < threshold && after >= threshold {
+            events::emit_stock_recovered(env, bank_id, blood_type, after, threshold);
+        }
+    }
 }
 
 #[cfg(test)]
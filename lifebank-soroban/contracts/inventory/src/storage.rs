@@ -1,4 +1,4 @@
-use crate::types::{BloodStatus, BloodUnit, DataKey, StatusChangeHistory};
+use crate::types::{BloodStatus, BloodType, BloodUnit, DataKey, PauseScope, StatusChangeHistory};
 use soroban_sdk::{Address, Env, String, Vec};
 
 /// Maximum expiration time (42 days for whole blood)
@@ -18,6 +18,32 @@ pub fn set_admin(env: &Env, admin: &Address) {
     env.storage().instance().set(&DataKey::Admin, admin);
 }
 
+/// Get the guardian address, if one has been appointed
+pub fn get_guardian(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::Guardian)
+}
+
+/// Set the guardian address
+pub fn set_guardian(env: &Env, guardian: &Address) {
+    env.storage().instance().set(&DataKey::Guardian, guardian);
+}
+
+/// Check if an operation family is paused, on its own or by `PauseScope::All`
+pub fn is_paused(env: &Env, scope: PauseScope) -> bool {
+    let storage = env.storage().instance();
+    storage.has(&DataKey::Paused(PauseScope::All)) || storage.has(&DataKey::Paused(scope))
+}
+
+/// Pause or unpause an operation family
+pub fn set_paused(env: &Env, scope: PauseScope, paused: bool) {
+    let key = DataKey::Paused(scope);
+    if paused {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+}
+
 /// Check if an address is authorized as a blood bank
 pub fn is_authorized_bank(env: &Env, bank: &Address) -> bool {
     let admin = get_admin(env);
@@ -54,9 +80,48 @@ pub fn get_blood_unit(env: &Env, id: u64) -> Option<BloodUnit> {
     env.storage().persistent().get(&DataKey::BloodUnit(id))
 }
 
-/// Check if a blood unit exists
-pub fn blood_unit_exists(env: &Env, id: u64) -> bool {
-    env.storage().persistent().has(&DataKey::BloodUnit(id))
+/// Set the blood unit counter (index repair only)
+pub fn set_blood_unit_counter(env: &Env, value: u64) {
+    env.storage()
+        .instance()
+        .set(&DataKey::BloodUnitCounter, &value);
+}
+
+/// Get the unit IDs stored under an index key
+pub fn get_index(env: &Env, key: &DataKey) -> Vec<u64> {
+    env.storage().persistent().get(key).unwrap_or(Vec::new(env))
+}
+
+/// Store the unit IDs of an index key
+pub fn set_index(env: &Env, key: &DataKey, units: &Vec<u64>) {
+    env.storage().persistent().set(key, units);
+}
+
+/// Insert an ID into a sorted index, ignoring duplicates
+pub fn insert_sorted(ids: &mut Vec<u64>, id: u64) {
+    if let Err(pos) = ids.binary_search(id) {
+        ids.insert(pos, id);
+    }
+}
+
+/// Move a blood unit between status indexes when its status changes
+pub fn update_status_index(
+    env: &Env,
+    unit_id: u64,
+    old_status: BloodStatus,
+    new_status: BloodStatus,
+) {
+    let old_key = DataKey::StatusIndex(old_status);
+    let mut old_units = get_index(env, &old_key);
+    if let Some(pos) = old_units.first_index_of(unit_id) {
+        old_units.remove(pos);
+        set_index(env, &old_key, &old_units);
+    }
+
+    let new_key = DataKey::StatusIndex(new_status);
+    let mut new_units = get_index(env, &new_key);
+    insert_sorted(&mut new_units, unit_id);
+    set_index(env, &new_key, &new_units);
 }
 
 /// Add blood unit to blood type index
@@ -167,6 +232,34 @@ pub fn get_blood_unit_status_change_count(env: &Env, blood_unit_id: u64) -> u64
     env.storage().persistent().get(&key).unwrap_or(0)
 }
 
+/// Get the number of Available units a bank holds for a blood type
+pub fn get_available_stock(env: &Env, bank: &Address, blood_type: BloodType) -> u32 {
+    let key = DataKey::AvailableStock(bank.clone(), blood_type);
+    env.storage().persistent().get(&key).unwrap_or(0)
+}
+
+/// Set the number of Available units a bank holds for a blood type
+pub fn set_available_stock(env: &Env, bank: &Address, blood_type: BloodType, units: u32) {
+    let key = DataKey::AvailableStock(bank.clone(), blood_type);
+    env.storage().persistent().set(&key, &units);
+}
+
+/// Get a bank's minimum stock threshold for a blood type (0 if unset)
+pub fn get_stock_threshold(env: &Env, bank: &Address, blood_type: BloodType) -> u32 {
+    let key = DataKey::StockThreshold(bank.clone(), blood_type);
+    env.storage().persistent().get(&key).unwrap_or(0)
+}
+
+/// Set a bank's minimum stock threshold for a blood type (0 removes it)
+pub fn set_stock_threshold(env: &Env, bank: &Address, blood_type: BloodType, min_units: u32) {
+    let key = DataKey::StockThreshold(bank.clone(), blood_type);
+    if min_units == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &min_units);
+    }
+}
+
 /// Get the next status history ID
 fn increment_status_history_counter(env: &Env) -> u64 {
     let key = DataKey::StatusHistoryCounter;
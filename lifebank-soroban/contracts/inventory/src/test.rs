@@ -1,9 +1,13 @@
+use crate::error::ContractError;
 use crate::storage;
-use crate::types::{BloodStatus, BloodType};
+use crate::types::{
+    BloodStatus, BloodType, DataKey, EntityId, EventEnvelope, EventPayload, IndexIssue, IndexKind,
+    IssueKind, PauseScope, EVENT_SCHEMA_VERSION,
+};
 use crate::{InventoryContract, InventoryContractClient};
 use soroban_sdk::{
-    testutils::{Address as _, Ledger},
-    vec, Address, Env, String,
+    testutils::{Address as _, Events, Ledger},
+    vec, Address, Env, String, Symbol, TryFromVal,
 };
 
 fn create_test_contract<'a>() -> (Env, Address, InventoryContractClient<'a>, Address) {
@@ -213,7 +217,7 @@ fn test_register_blood_insufficient_shelf_life() {
 #[test]
 #[should_panic(expected = "Error(Contract, #32)")]
 fn test_register_blood_unauthorized_bank() {
-    let (env, admin, client, _contract_id) = create_test_contract();
+    let (env, _admin, client, _contract_id) = create_test_contract();
 
     let unauthorized_bank = Address::generate(&env);
     let current_time = 1000u64;
@@ -427,7 +431,7 @@ fn test_update_status_unauthorized() {
 #[test]
 #[should_panic(expected = "Error(Contract, #21)")]
 fn test_update_status_nonexistent_unit() {
-    let (env, admin, client, _contract_id) = create_test_contract();
+    let (_env, admin, client, _contract_id) = create_test_contract();
 
     // Try to update unit that doesn't exist
     client.update_status(&999, &BloodStatus::Reserved, &admin, &None);
@@ -758,3 +762,466 @@ fn test_batch_update_status_invalid_transition() {
         &None,
     );
 }
+
+fn count_stock_alerts(env: &Env, name: &str) -> u32 {
+    let topic = Symbol::new(env, name);
+    let mut count = 0;
+    for (_, topics, _) in env.events().all().iter() {
+        if let Some(first) = topics.get(0) {
+            if let Ok(sym) = Symbol::try_from_val(env, &first) {
+                if sym == topic {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn test_available_stock_tracks_status_changes() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+
+    let id1 = client.register_blood(&bank, &BloodType::APositive, &450u32, &expiration, &None);
+    client.register_blood(&bank, &BloodType::APositive, &450u32, &expiration, &None);
+    assert_eq!(client.get_available_stock(&bank, &BloodType::APositive), 2);
+
+    client.update_status(&id1, &BloodStatus::Reserved, &admin, &None);
+    assert_eq!(client.get_available_stock(&bank, &BloodType::APositive), 1);
+
+    client.update_status(&id1, &BloodStatus::Available, &admin, &None);
+    assert_eq!(client.get_available_stock(&bank, &BloodType::APositive), 2);
+    assert_eq!(client.get_available_stock(&bank, &BloodType::ONegative), 0);
+}
+
+#[test]
+fn test_stock_threshold_emits_shortage_and_recovered() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+
+    let id1 = client.register_blood(&bank, &BloodType::ONegative, &450u32, &expiration, &None);
+    client.register_blood(&bank, &BloodType::ONegative, &450u32, &expiration, &None);
+
+    client.set_stock_threshold(&bank, &BloodType::ONegative, &2u32);
+    assert_eq!(client.get_stock_threshold(&bank, &BloodType::ONegative), 2);
+    assert_eq!(count_stock_alerts(&env, "shortage"), 0);
+
+    // Dropping below the threshold emits a shortage alert
+    client.update_status(&id1, &BloodStatus::Reserved, &admin, &None);
+    assert_eq!(count_stock_alerts(&env, "shortage"), 1);
+
+    // Climbing back to the threshold emits a recovered alert
+    client.update_status(&id1, &BloodStatus::Available, &admin, &None);
+    assert_eq!(count_stock_alerts(&env, "recovered"), 1);
+}
+
+#[test]
+fn test_stock_threshold_already_short_emits_shortage() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+
+    client.register_blood(&bank, &BloodType::BNegative, &450u32, &expiration, &None);
+
+    client.set_stock_threshold(&bank, &BloodType::BNegative, &3u32);
+    assert_eq!(count_stock_alerts(&env, "shortage"), 1);
+
+    // Still below the threshold, no repeated alert
+    client.register_blood(&bank, &BloodType::BNegative, &450u32, &expiration, &None);
+    assert_eq!(count_stock_alerts(&env, "shortage"), 0);
+}
+
+#[test]
+fn test_lowering_stock_threshold_emits_recovered() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+
+    client.register_blood(&bank, &BloodType::ABNegative, &450u32, &expiration, &None);
+    client.register_blood(&bank, &BloodType::ABNegative, &450u32, &expiration, &None);
+
+    client.set_stock_threshold(&bank, &BloodType::ABNegative, &3u32);
+    assert_eq!(count_stock_alerts(&env, "shortage"), 1);
+
+    // Lowering the threshold to the stock on hand ends the shortage
+    client.set_stock_threshold(&bank, &BloodType::ABNegative, &2u32);
+    assert_eq!(count_stock_alerts(&env, "recovered"), 1);
+
+    // Lowering it further while not short emits nothing
+    client.set_stock_threshold(&bank, &BloodType::ABNegative, &1u32);
+    assert_eq!(count_stock_alerts(&env, "recovered"), 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #32)")]
+fn test_set_stock_threshold_unauthorized_bank() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+
+    let unauthorized = Address::generate(&env);
+    client.set_stock_threshold(&unauthorized, &BloodType::APositive, &5u32);
+}
+
+#[test]
+fn test_expired_unit_can_be_marked_expired() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+
+    let unit_id = client.register_blood(&bank, &BloodType::APositive, &450u32, &expiration, &None);
+
+    env.ledger().set_timestamp(expiration + 1);
+    client.update_status(&unit_id, &BloodStatus::Expired, &admin, &None);
+
+    assert_eq!(client.get_blood_unit(&unit_id).status, BloodStatus::Expired);
+    assert_eq!(client.get_available_stock(&bank, &BloodType::APositive), 0);
+}
+
+#[test]
+fn test_events_use_versioned_envelope() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let bank = admin.clone();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let unit_id = client.register_blood(&bank, &BloodType::ONegative, &450u32, &expiration, &None);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    let topic = Symbol::try_from_val(&env, &topics.get(0).unwrap()).unwrap();
+    assert_eq!(topic, Symbol::new(&env, "blood_registered"));
+
+    let envelope = EventEnvelope::try_from_val(&env, &data).unwrap();
+    assert_eq!(envelope.version, EVENT_SCHEMA_VERSION);
+    assert_eq!(envelope.entity_type, Symbol::new(&env, "unit"));
+    assert_eq!(envelope.entity_id, EntityId::Record(unit_id));
+    assert_eq!(envelope.actor, Some(bank.clone()));
+    assert_eq!(envelope.timestamp, 1000);
+    match envelope.payload {
+        EventPayload::BloodRegistered(event) => assert_eq!(event.blood_unit_id, unit_id),
+        _ => panic!("unexpected payload"),
+    }
+
+    client.set_stock_threshold(&bank, &BloodType::ONegative, &3);
+    let (_, _, data) = env.events().all().first().unwrap();
+    let envelope = EventEnvelope::try_from_val(&env, &data).unwrap();
+    assert_eq!(envelope.entity_id, EntityId::Account(bank));
+    match envelope.payload {
+        EventPayload::ThresholdSet(event) => assert_eq!(event.threshold_units, 3),
+        _ => panic!("unexpected payload"),
+    }
+}
+
+fn issue(index: IndexKind, kind: IssueKind, unit_id: u64) -> IndexIssue {
+    IndexIssue {
+        index,
+        kind,
+        unit_id,
+        expected: 0,
+        actual: 0,
+    }
+}
+
+#[test]
+fn test_update_status_moves_unit_between_status_indexes() {
+    let (env, admin, client, contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let first = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+    let second = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+
+    client.update_status(&second, &BloodStatus::Reserved, &admin, &None);
+    client.batch_update_status(&vec![&env, first], &BloodStatus::Reserved, &admin, &None);
+    client.update_status(&first, &BloodStatus::InTransit, &admin, &None);
+
+    env.as_contract(&contract_id, || {
+        let index = |status| storage::get_index(&env, &DataKey::StatusIndex(status));
+        assert!(index(BloodStatus::Available).is_empty());
+        assert_eq!(index(BloodStatus::Reserved), vec![&env, second]);
+        assert_eq!(index(BloodStatus::InTransit), vec![&env, first]);
+    });
+}
+
+#[test]
+fn test_verify_invariants_clean_inventory_pages() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let donor = Address::generate(&env);
+    for i in 0..5u64 {
+        let donor_id = if i % 2 == 0 {
+            Some(donor.clone())
+        } else {
+            None
+        };
+        let unit_id = client.register_blood(
+            &admin,
+            &BloodType::ONegative,
+            &450u32,
+            &expiration,
+            &donor_id,
+        );
+        if i == 3 {
+            client.update_status(&unit_id, &BloodStatus::Reserved, &admin, &None);
+        }
+    }
+
+    let page = client.verify_invariants(&0, &2);
+    assert!(page.issues.is_empty());
+    assert_eq!(page.next_cursor, 3);
+
+    let page = client.verify_invariants(&page.next_cursor, &2);
+    assert!(page.issues.is_empty());
+    assert_eq!(page.next_cursor, 5);
+
+    let page = client.verify_invariants(&page.next_cursor, &2);
+    assert!(page.issues.is_empty());
+    assert_eq!(page.next_cursor, 0);
+}
+
+#[test]
+fn test_verify_invariants_reports_drift_and_repair_fixes_it() {
+    let (env, admin, client, contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let first = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+    let second = client.register_blood(&admin, &BloodType::BPositive, &450u32, &expiration, &None);
+    client.update_status(&second, &BloodStatus::Reserved, &admin, &None);
+
+    // Simulate drift: a lost type entry, a stale status entry, an entry
+    // without a record, an unsorted bank index and a wrong stock counter
+    env.as_contract(&contract_id, || {
+        storage::set_index(
+            &env,
+            &DataKey::BloodTypeIndex(BloodType::APositive),
+            &vec![&env],
+        );
+        storage::set_index(
+            &env,
+            &DataKey::StatusIndex(BloodStatus::Available),
+            &vec![&env, first, second],
+        );
+        storage::set_index(
+            &env,
+            &DataKey::StatusIndex(BloodStatus::Expired),
+            &vec![&env, 9],
+        );
+        storage::set_index(
+            &env,
+            &DataKey::BankIndex(admin.clone()),
+            &vec![&env, second, first],
+        );
+        storage::set_available_stock(&env, &admin, BloodType::APositive, 4);
+    });
+
+    let expected = vec![
+        &env,
+        issue(IndexKind::BloodType, IssueKind::MissingEntry, first),
+        issue(IndexKind::Bank, IssueKind::Unsorted, 0),
+        issue(IndexKind::Status, IssueKind::StaleEntry, second),
+        issue(IndexKind::Status, IssueKind::DanglingEntry, 9),
+        IndexIssue {
+            index: IndexKind::AvailableStock,
+            kind: IssueKind::CounterMismatch,
+            unit_id: first,
+            expected: 1,
+            actual: 4,
+        },
+    ];
+
+    let report = client.verify_invariants(&0, &10);
+    assert_eq!(report.issues, expected);
+    assert_eq!(report.next_cursor, 0);
+
+    // Verification is read-only
+    assert_eq!(client.verify_invariants(&0, &10).issues, expected);
+
+    let repaired = client.repair_indexes(&admin, &0, &10);
+    assert_eq!(repaired.issues, expected);
+
+    let (_, _, data) = env.events().all().last().unwrap();
+    let envelope = EventEnvelope::try_from_val(&env, &data).unwrap();
+    match envelope.payload {
+        EventPayload::IndexesRepaired(event) => {
+            assert_eq!(event.from_id, 1);
+            assert_eq!(event.to_id, second);
+            assert_eq!(event.issues_fixed, 5);
+        }
+        _ => panic!("unexpected payload"),
+    }
+
+    assert!(client.verify_invariants(&0, &10).issues.is_empty());
+    assert_eq!(client.get_available_stock(&admin, &BloodType::APositive), 1);
+}
+
+#[test]
+fn test_verify_invariants_detects_lagging_unit_counter() {
+    let (env, admin, client, contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+    client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+
+    env.as_contract(&contract_id, || storage::set_blood_unit_counter(&env, 1));
+
+    let report = client.verify_invariants(&0, &10);
+    assert_eq!(
+        report.issues.first().unwrap(),
+        IndexIssue {
+            index: IndexKind::UnitCounter,
+            kind: IssueKind::CounterMismatch,
+            unit_id: 0,
+            expected: 2,
+            actual: 1,
+        }
+    );
+
+    client.repair_indexes(&admin, &0, &10);
+    assert!(client.verify_invariants(&0, &10).issues.is_empty());
+    let next = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+    assert_eq!(next, 3);
+}
+
+#[test]
+fn test_invariant_entry_points_reject_bad_input() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+
+    assert_eq!(
+        client.try_verify_invariants(&0, &0),
+        Err(Ok(ContractError::InvalidInput))
+    );
+    assert_eq!(
+        client.try_verify_invariants(&0, &101),
+        Err(Ok(ContractError::InvalidInput))
+    );
+
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        client.try_repair_indexes(&stranger, &0, &10),
+        Err(Ok(ContractError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_pause_blocks_only_the_paused_family() {
+    let (env, admin, client, contract_id) = create_test_contract();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let unit_id = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+
+    let reason = String::from_str(&env, "allocation bug under investigation");
+    client.pause(&admin, &PauseScope::Allocation, &reason);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    let topic = Symbol::try_from_val(&env, &topics.get(0).unwrap()).unwrap();
+    assert_eq!(topic, Symbol::new(&env, "paused"));
+    let envelope = EventEnvelope::try_from_val(&env, &data).unwrap();
+    assert_eq!(envelope.entity_id, EntityId::Account(contract_id));
+    assert_eq!(envelope.actor, Some(admin.clone()));
+    match envelope.payload {
+        EventPayload::PauseChanged(event) => {
+            assert_eq!(event.scope, PauseScope::Allocation);
+            assert!(event.paused);
+            assert_eq!(event.reason, reason);
+        }
+        _ => panic!("unexpected payload"),
+    }
+
+    assert_eq!(
+        client
+            .try_update_status(&unit_id, &BloodStatus::Reserved, &admin, &None)
+            .unwrap_err(),
+        Ok(ContractError::ContractPaused)
+    );
+    assert!(client.is_paused(&PauseScope::Allocation));
+    assert!(!client.is_paused(&PauseScope::Registration));
+
+    // Registration, reads and expiry keep working
+    let second = client.register_blood(&admin, &BloodType::APositive, &450u32, &expiration, &None);
+    assert_eq!(
+        client.get_blood_unit(&unit_id).status,
+        BloodStatus::Available
+    );
+    client.mark_expired(&second, &admin);
+
+    client.unpause(
+        &admin,
+        &PauseScope::Allocation,
+        &String::from_str(&env, "fixed"),
+    );
+    client.update_status(&unit_id, &BloodStatus::Reserved, &admin, &None);
+    assert_eq!(
+        client.get_blood_unit(&unit_id).status,
+        BloodStatus::Reserved
+    );
+}
+
+#[test]
+fn test_guardian_pause_all_blocks_every_family() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+    env.ledger().set_timestamp(1000);
+    let expiration = 1000 + (30 * 86400);
+    let unit_id = client.register_blood(&admin, &BloodType::ONegative, &450u32, &expiration, &None);
+    client.update_status(&unit_id, &BloodStatus::Reserved, &admin, &None);
+
+    let guardian = Address::generate(&env);
+    client.set_guardian(&guardian);
+    assert_eq!(client.get_guardian(), Some(guardian.clone()));
+
+    let reason = String::from_str(&env, "compromised bank key");
+    client.pause(&guardian, &PauseScope::All, &reason);
+    assert!(client.is_paused(&PauseScope::Transfers));
+
+    assert_eq!(
+        client.try_register_blood(&admin, &BloodType::ONegative, &450u32, &expiration, &None),
+        Err(Ok(ContractError::ContractPaused))
+    );
+    assert_eq!(
+        client.try_batch_update_status(
+            &vec![&env, unit_id],
+            &BloodStatus::InTransit,
+            &admin,
+            &None
+        ),
+        Err(Ok(ContractError::ContractPaused))
+    );
+
+    // Only the admin can lift a pause
+    assert_eq!(
+        client.try_unpause(&guardian, &PauseScope::All, &reason),
+        Err(Ok(ContractError::Unauthorized))
+    );
+    client.unpause(&admin, &PauseScope::All, &reason);
+    client.update_status(&unit_id, &BloodStatus::InTransit, &admin, &None);
+}
+
+#[test]
+fn test_pause_requires_admin_or_guardian() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let stranger = Address::generate(&env);
+
+    assert_eq!(
+        client.try_pause(
+            &stranger,
+            &PauseScope::Registration,
+            &String::from_str(&env, "junk units")
+        ),
+        Err(Ok(ContractError::Unauthorized))
+    );
+    assert!(!client.is_paused(&PauseScope::Registration));
+}
@@ -247,6 +247,18 @@ pub enum DataKey {
 
     /// Counter for status changes on specific blood unit
     BloodUnitStatusChangeCount(u64), // u64 is blood_unit_id
+
+    /// Number of Available units a bank holds for a blood type
+    AvailableStock(Address, BloodType),
+
+    /// Minimum Available units a bank wants to keep for a blood type
+    StockThreshold(Address, BloodType),
+
+    /// Guardian who may pause operations alongside the admin
+    Guardian,
+
+    /// Set while an operation family is paused
+    Paused(PauseScope),
 }
 
 #[contracttype]
@@ -294,6 +306,99 @@ pub struct StatusChangeEvent {
     pub reason: Option<String>,
 }
 
+/// Event emitted when a bank's available stock crosses its threshold
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct StockAlertEvent {
+    /// Blood bank whose stock crossed the threshold
+    pub bank_id: Address,
+
+    /// Blood type concerned
+    pub blood_type: BloodType,
+
+    /// Available units after the change
+    pub available_units: u32,
+
+    /// Configured minimum number of available units
+    pub threshold_units: u32,
+
+    /// When the threshold was crossed
+    pub timestamp: u64,
+}
+
+/// Event emitted when a bank configures its low-stock threshold
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ThresholdSetEvent {
+    /// Blood bank that set the threshold
+    pub bank_id: Address,
+
+    /// Blood type the threshold applies to
+    pub blood_type: BloodType,
+
+    /// New minimum number of available units (0 disables alerts)
+    pub threshold_units: u32,
+}
+
+/// Event emitted when the contract is initialized
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct InitializedEvent {
+    /// Admin address set at initialization
+    pub admin: Address,
+}
+
+/// Version of the `EventEnvelope` layout; bump on incompatible payload changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Identifier of the entity an event is about
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntityId {
+    /// Numeric record id (blood unit)
+    Record(u64),
+
+    /// Account address (admin, blood bank)
+    Account(Address),
+}
+
+/// Typed payload carried by an `EventEnvelope`
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum EventPayload {
+    Initialized(InitializedEvent),
+    BloodRegistered(BloodRegisteredEvent),
+    StatusChange(StatusChangeEvent),
+    StockAlert(StockAlertEvent),
+    ThresholdSet(ThresholdSetEvent),
+    IndexesRepaired(IndexesRepairedEvent),
+    GuardianSet(GuardianSetEvent),
+    PauseChanged(PauseChangedEvent),
+}
+
+/// Common envelope wrapping every event published by the contract
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct EventEnvelope {
+    /// Schema version (`EVENT_SCHEMA_VERSION`)
+    pub version: u32,
+
+    /// Kind of entity the event is about ("unit", "bank", "admin")
+    pub entity_type: Symbol,
+
+    /// Id of that entity
+    pub entity_id: EntityId,
+
+    /// Address that triggered the change, if any
+    pub actor: Option<Address>,
+
+    /// Ledger timestamp when the event was published
+    pub timestamp: u64,
+
+    /// Typed event data
+    pub payload: EventPayload,
+}
+
 /// Historical record of a status change
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -334,6 +439,128 @@ pub struct BatchStatusUpdate {
     pub reason: Option<String>,
 }
 
+/// Operation families that can be paused on their own
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseScope {
+    /// Every family below
+    All,
+    /// Registering new blood units
+    Registration,
+    /// Reserving units and releasing them back to Available
+    Allocation,
+    /// Dispatching and delivering units
+    Transfers,
+}
+
+/// Event emitted when the admin appoints a guardian
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct GuardianSetEvent {
+    /// Address that may now pause operations
+    pub guardian: Address,
+}
+
+/// Event emitted when an operation family is paused or unpaused
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct PauseChangedEvent {
+    /// Family the change applies to
+    pub scope: PauseScope,
+
+    /// Whether the family is now paused
+    pub paused: bool,
+
+    /// Why the pause was raised or lifted
+    pub reason: String,
+}
+
+/// Record, index or counter checked by `verify_invariants`
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IndexKind {
+    /// The blood unit record itself
+    Record,
+    /// `BloodTypeIndex`
+    BloodType,
+    /// `BankIndex`
+    Bank,
+    /// `StatusIndex`
+    Status,
+    /// `DonorIndex`
+    Donor,
+    /// `BloodUnitCounter`
+    UnitCounter,
+    /// `AvailableStock` for a bank and blood type
+    AvailableStock,
+}
+
+/// Kind of mismatch between records, indexes and counters
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IssueKind {
+    /// An ID at or below the counter has no record
+    MissingRecord,
+    /// The record is missing from an index it belongs in
+    MissingEntry,
+    /// An index lists the record under a key that no longer matches it
+    StaleEntry,
+    /// An index lists an ID that has no record
+    DanglingEntry,
+    /// An index is not in strictly ascending ID order
+    Unsorted,
+    /// A counter disagrees with the records
+    CounterMismatch,
+}
+
+/// One inconsistency found by `verify_invariants`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexIssue {
+    /// Record, index or counter the issue is in
+    pub index: IndexKind,
+
+    /// What is wrong
+    pub kind: IssueKind,
+
+    /// Blood unit ID concerned (0 for whole-index issues)
+    pub unit_id: u64,
+
+    /// Value the counter should hold (`CounterMismatch` only)
+    pub expected: u64,
+
+    /// Value the counter holds (`CounterMismatch` only)
+    pub actual: u64,
+}
+
+/// One page of invariant checks
+///
+/// Pass `next_cursor` back as `cursor` to check the following page.
+/// A `next_cursor` of 0 means every unit has been checked.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvariantReport {
+    /// Issues found on this page; for `repair_indexes`, the issues fixed
+    pub issues: Vec<IndexIssue>,
+
+    /// First unit ID of the next page, or 0 when done
+    pub next_cursor: u64,
+}
+
+/// Event emitted when the admin repairs index drift
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct IndexesRepairedEvent {
+    /// First unit ID of the repaired page
+    pub from_id: u64,
+
+    /// Last unit ID of the repaired page
+    pub to_id: u64,
+
+    /// Number of issues fixed
+    pub issues_fixed: u32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -15,7 +15,7 @@ pub fn validate_blood_registration(
     expiration_timestamp: u64,
 ) -> Result<(), ContractError> {
     // Validate quantity (typical range: 100-600ml)
-    if quantity_ml < 100 || quantity_ml > 600 {
+    if !(100..=600).contains(&quantity_ml) {
         return Err(ContractError::InvalidQuantity);
     }
 
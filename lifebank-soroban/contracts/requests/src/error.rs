@@ -69,6 +69,12 @@ pub enum ContractError {
     /// Duplicate request detected
     DuplicateRequest = 24,
 
+    /// The operation's family is paused
+    ContractPaused = 25,
+
+    /// Hospital is over its request rate limit or open-request cap
+    RequestLimitExceeded = 26,
+
     // ========== Permission errors (30-39) ==========
     /// Insufficient balance for operation
     InsufficientBalance = 30,
@@ -106,37 +112,10 @@ pub enum ContractError {
 
     /// Request is not yet approved
     RequestNotApproved = 47,
-    // General errors (0-9)
-    AlreadyInitialized = 0,
-    NotInitialized = 1,
-    Unauthorized = 2,
-
-    // Validation errors (10-19)
-    InvalidAmount = 10,
-    InvalidAddress = 11,
-    InvalidInput = 12,
-    InvalidBloodType = 13,
-    InvalidStatus = 14,
-    InvalidTimestamp = 15,
-    InvalidQuantity = 16,
-    InvalidExpiration = 17,
-
-    // State errors (20-29)
-    AlreadyExists = 20,
-    NotFound = 21,
-    Expired = 22,
-    RequestExpired = 23,
-    DuplicateRequest = 24,
 
-    // Permission errors (30-39)
-    InsufficientPermissions = 31,
-    NotAuthorizedHospital = 32,
-    NotAuthorizedBloodBank = 33,
+    /// Order has no lines, too many lines, or an invalid line index
+    InvalidOrderLine = 48,
 
-    // Request-specific errors (40-49)
-    RequestNotFound = 40,
-    InvalidStatusTransition = 41,
-    RequestAlreadyFulfilled = 42,
-    InsufficientBloodUnits = 43,
-    RequestOverdue = 44,
+    /// Order with given ID doesn't exist
+    OrderNotFound = 49,
 }
@@ -1,8 +1,97 @@
 use crate::types::{
-    BloodType, RequestCreatedEvent, RequestStatus, RequestStatusChangedEvent, UnitsAssignedEvent,
-    UrgencyLevel,
+    BloodType, EntityId, EventEnvelope, EventPayload, GuardianSetEvent, HospitalAuthorizationEvent,
+    IndexesRepairedEvent, InitializedEvent, OrderCreatedEvent, OrderLineUpdatedEvent, OrderStatus,
+    PauseChangedEvent, PauseScope, RateLimit, RateLimitSetEvent, RequestCreatedEvent,
+    RequestOverLimitEvent, RequestOverdueEvent, RequestStatus, RequestStatusChangedEvent,
+    UnitsAssignedEvent, UnitsReleasedEvent, UrgencyEscalatedEvent, UrgencyLevel,
+    EVENT_SCHEMA_VERSION,
 };
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use soroban_sdk::{Address, Env, String, Symbol, Vec};
+
+/// Publish a payload wrapped in the versioned event envelope
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `topic` - Event topic
+/// * `entity_type` - Kind of entity the event is about
+/// * `entity_id` - Id of that entity
+/// * `actor` - Address that triggered the change, if any
+/// * `payload` - Typed event data
+fn publish(
+    env: &Env,
+    topic: &str,
+    entity_type: &str,
+    entity_id: EntityId,
+    actor: Option<&Address>,
+    payload: EventPayload,
+) {
+    let envelope = EventEnvelope {
+        version: EVENT_SCHEMA_VERSION,
+        entity_type: Symbol::new(env, entity_type),
+        entity_id,
+        actor: actor.cloned(),
+        timestamp: env.ledger().timestamp(),
+        payload,
+    };
+
+    // The envelope is a contracttype rather than a #[contractevent] so decoders
+    // can read every event through one schema
+    #[allow(deprecated)]
+    env.events().publish((Symbol::new(env, topic),), envelope);
+}
+
+/// Emit an Initialized event
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin address set at initialization
+pub fn emit_initialized(env: &Env, admin: &Address) {
+    let event = InitializedEvent {
+        admin: admin.clone(),
+    };
+
+    publish(
+        env,
+        "initialized",
+        "admin",
+        EntityId::Account(admin.clone()),
+        Some(admin),
+        EventPayload::Initialized(event),
+    );
+}
+
+/// Emit a HospitalAuthorization event when the admin authorizes or revokes a hospital
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin that made the change
+/// * `hospital` - Hospital whose authorization changed
+/// * `authorized` - Whether the hospital is now authorized
+pub fn emit_hospital_authorization(
+    env: &Env,
+    admin: &Address,
+    hospital: &Address,
+    authorized: bool,
+) {
+    let event = HospitalAuthorizationEvent {
+        hospital: hospital.clone(),
+        authorized,
+    };
+    let topic = if authorized {
+        "hospital_authorized"
+    } else {
+        "hospital_revoked"
+    };
+
+    publish(
+        env,
+        topic,
+        "hospital",
+        EntityId::Account(hospital.clone()),
+        Some(admin),
+        EventPayload::HospitalAuthorization(event),
+    );
+}
 
 /// Emit a RequestCreated event when a new blood request is submitted
 ///
@@ -14,42 +103,6 @@ use soroban_sdk::{Address, Env, Symbol, Vec};
 /// * `quantity_ml` - Quantity requested in milliliters
 /// * `urgency` - Urgency level of the request
 /// * `required_by` - Timestamp when blood is required
-use crate::types::{BloodType, RequestStatus, UrgencyLevel};
-use soroban_sdk::{Address, Env, Symbol};
-
-/// Event emitted when a blood request is created
-#[soroban_sdk::contracttype]
-#[derive(Clone)]
-pub struct RequestCreatedEvent {
-    pub request_id: u64,
-    pub hospital_id: Address,
-    pub blood_type: BloodType,
-    pub quantity_ml: u32,
-    pub urgency: UrgencyLevel,
-    pub required_by: u64,
-    pub created_at: u64,
-}
-
-/// Event emitted when a request status changes
-#[soroban_sdk::contracttype]
-#[derive(Clone)]
-pub struct RequestStatusChangedEvent {
-    pub request_id: u64,
-    pub old_status: RequestStatus,
-    pub new_status: RequestStatus,
-    pub changed_at: u64,
-}
-
-/// Event emitted when blood units are assigned to a request
-#[soroban_sdk::contracttype]
-#[derive(Clone)]
-pub struct UnitsAssignedEvent {
-    pub request_id: u64,
-    pub assigned_units: soroban_sdk::Vec<u64>,
-    pub assigned_at: u64,
-}
-
-/// Emit a RequestCreated event
 pub fn emit_request_created(
     env: &Env,
     request_id: u64,
@@ -71,8 +124,14 @@ pub fn emit_request_created(
         created_at,
     };
 
-    env.events()
-        .publish((Symbol::new(env, "request_created"),), event);
+    publish(
+        env,
+        "request_created",
+        "request",
+        EntityId::Record(request_id),
+        Some(hospital_id),
+        EventPayload::RequestCreated(event),
+    );
 }
 
 /// Emit a RequestStatusChanged event when request status is updated
@@ -82,13 +141,13 @@ pub fn emit_request_created(
 /// * `request_id` - ID of the request
 /// * `old_status` - Previous status
 /// * `new_status` - New status
-pub fn emit_status_changed(
-/// Emit a RequestStatusChanged event
+/// * `actor` - Address that triggered the change, if any
 pub fn emit_request_status_changed(
     env: &Env,
     request_id: u64,
     old_status: RequestStatus,
     new_status: RequestStatus,
+    actor: Option<&Address>,
 ) {
     let changed_at = env.ledger().timestamp();
 
@@ -99,51 +158,54 @@ pub fn emit_request_status_changed(
         changed_at,
     };
 
-    env.events()
-        .publish((Symbol::new(env, "status_changed"),), event);
+    publish(
+        env,
+        "status_changed",
+        "request",
+        EntityId::Record(request_id),
+        actor,
+        EventPayload::RequestStatusChanged(event),
+    );
 }
 
-/// Emit an UnitsAssigned event when blood units are assigned to a request
+/// Emit a UnitsAssigned event when the admin assigns blood units to a request
 ///
 /// # Arguments
 /// * `env` - Contract environment
 /// * `request_id` - ID of the request
 /// * `unit_ids` - IDs of the assigned blood units
-/// * `total_quantity_ml` - Total quantity assigned in milliliters
-#[allow(dead_code)]
-pub fn emit_units_assigned(
-    env: &Env,
-    request_id: u64,
-    unit_ids: Vec<u64>,
-    total_quantity_ml: u32,
-        .publish((Symbol::new(env, "request_status_changed"),), event);
-}
-
-/// Emit an UnitsAssigned event
-pub fn emit_units_assigned(
-    env: &Env,
-    request_id: u64,
-    assigned_units: soroban_sdk::Vec<u64>,
-) {
-    let assigned_at = env.ledger().timestamp();
-
+/// * `admin` - Admin who assigned the units
+pub fn emit_units_assigned(env: &Env, request_id: u64, unit_ids: Vec<u64>, admin: &Address) {
     let event = UnitsAssignedEvent {
         request_id,
         unit_ids,
-        total_quantity_ml,
-        assigned_units,
-        assigned_at,
+        assigned_at: env.ledger().timestamp(),
     };
 
-    env.events()
-        .publish((Symbol::new(env, "units_assigned"),), event);
+    publish(
+        env,
+        "units_assigned",
+        "request",
+        EntityId::Record(request_id),
+        Some(admin),
+        EventPayload::UnitsAssigned(event),
+    );
 }
 
-/// Emit a request approved event
+/// Emit a status change from Pending to Approved
 ///
-/// This is a simplified event using just the request ID
-pub fn emit_request_approved(env: &Env, request_id: u64) {
-    emit_status_changed(env, request_id, RequestStatus::Pending, RequestStatus::Approved);
+/// # Arguments
+/// * `env` - Contract environment
+/// * `request_id` - ID of the approved request
+/// * `admin` - Admin who approved the request
+pub fn emit_request_approved(env: &Env, request_id: u64, admin: &Address) {
+    emit_request_status_changed(
+        env,
+        request_id,
+        RequestStatus::Pending,
+        RequestStatus::Approved,
+        Some(admin),
+    );
 }
 
 /// Emit a request cancelled event
@@ -152,8 +214,20 @@ pub fn emit_request_approved(env: &Env, request_id: u64) {
 /// * `env` - Contract environment
 /// * `request_id` - ID of the cancelled request
 /// * `previous_status` - Status before cancellation
-pub fn emit_request_cancelled(env: &Env, request_id: u64, previous_status: RequestStatus) {
-    emit_status_changed(env, request_id, previous_status, RequestStatus::Cancelled);
+/// * `caller` - Hospital or admin that cancelled the request
+pub fn emit_request_cancelled(
+    env: &Env,
+    request_id: u64,
+    previous_status: RequestStatus,
+    caller: &Address,
+) {
+    emit_request_status_changed(
+        env,
+        request_id,
+        previous_status,
+        RequestStatus::Cancelled,
+        Some(caller),
+    );
 }
 
 /// Emit a request expired event
@@ -162,18 +236,296 @@ pub fn emit_request_cancelled(env: &Env, request_id: u64, previous_status: Reque
 /// * `env` - Contract environment
 /// * `request_id` - ID of the expired request
 /// * `previous_status` - Status before expiration
-#[allow(dead_code)]
 pub fn emit_request_expired(env: &Env, request_id: u64, previous_status: RequestStatus) {
-    emit_status_changed(env, request_id, previous_status, RequestStatus::Expired);
+    emit_request_status_changed(env, request_id, previous_status, RequestStatus::Expired, None);
+}
+
+/// Emit an UrgencyEscalated event when a request's deadline draws near
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `request_id` - ID of the escalated request
+/// * `old_urgency` - Urgency before escalation
+/// * `new_urgency` - Urgency after escalation
+/// * `required_by` - Deadline of the request
+pub fn emit_urgency_escalated(
+    env: &Env,
+    request_id: u64,
+    old_urgency: UrgencyLevel,
+    new_urgency: UrgencyLevel,
+    required_by: u64,
+) {
+    let event = UrgencyEscalatedEvent {
+        request_id,
+        old_urgency,
+        new_urgency,
+        required_by,
+        escalated_at: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        "urgency_escalated",
+        "request",
+        EntityId::Record(request_id),
+        None,
+        EventPayload::UrgencyEscalated(event),
+    );
+}
+
+/// Emit a RequestOverdue event when an open request passes its deadline
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `request_id` - ID of the overdue request
+/// * `hospital_id` - Hospital that created the request
+/// * `required_by` - Deadline that was missed
+pub fn emit_request_overdue(env: &Env, request_id: u64, hospital_id: &Address, required_by: u64) {
+    let event = RequestOverdueEvent {
+        request_id,
+        hospital_id: hospital_id.clone(),
+        required_by,
+        flagged_at: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        "request_overdue",
+        "request",
+        EntityId::Record(request_id),
+        None,
+        EventPayload::RequestOverdue(event),
+    );
+}
+
+/// Emit a UnitsReleased event when assigned units are returned from a request
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `request_id` - ID of the request
+/// * `unit_ids` - IDs of the released blood units
+pub fn emit_units_released(env: &Env, request_id: u64, unit_ids: Vec<u64>) {
+    let event = UnitsReleasedEvent {
+        request_id,
+        unit_ids,
+        released_at: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        "units_released",
+        "request",
+        EntityId::Record(request_id),
+        None,
+        EventPayload::UnitsReleased(event),
+    );
+}
+
+/// Emit an OrderCreated event when a multi-line order is submitted
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `order_id` - Unique ID of the created order
+/// * `hospital_id` - Hospital that created the order
+/// * `line_count` - Number of line items
+/// * `urgency` - Urgency level of the order
+/// * `required_by` - Timestamp when blood is required
+pub fn emit_order_created(
+    env: &Env,
+    order_id: u64,
+    hospital_id: &Address,
+    line_count: u32,
+    urgency: UrgencyLevel,
+    required_by: u64,
+) {
+    let event = OrderCreatedEvent {
+        order_id,
+        hospital_id: hospital_id.clone(),
+        line_count,
+        urgency,
+        required_by,
+        created_at: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        "order_created",
+        "order",
+        EntityId::Record(order_id),
+        Some(hospital_id),
+        EventPayload::OrderCreated(event),
+    );
+}
+
+/// Emit an OrderLineUpdated event when units are assigned or a line is cancelled
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `order_id` - ID of the order
+/// * `line_index` - Index of the line within the order
+/// * `line_status` - New status of the line
+/// * `order_status` - Derived status of the whole order
+/// * `actor` - Admin assigning units or caller cancelling the order
+pub fn emit_order_line_updated(
+    env: &Env,
+    order_id: u64,
+    line_index: u32,
+    line_status: OrderStatus,
+    order_status: OrderStatus,
+    actor: &Address,
+) {
+    let event = OrderLineUpdatedEvent {
+        order_id,
+        line_index,
+        line_status,
+        order_status,
+        updated_at: env.ledger().timestamp(),
+    };
+
+    publish(
+        env,
+        "order_line_updated",
+        "order",
+        EntityId::Record(order_id),
+        Some(actor),
+        EventPayload::OrderLineUpdated(event),
+    );
+}
+
+/// Emit an IndexesRepaired event after the admin fixes index drift
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin who ran the repair
+/// * `from_id` - First request ID of the repaired page
+/// * `to_id` - Last request ID of the repaired page
+/// * `issues_fixed` - Number of issues fixed
+pub fn emit_indexes_repaired(
+    env: &Env,
+    admin: &Address,
+    from_id: u64,
+    to_id: u64,
+    issues_fixed: u32,
+) {
+    let event = IndexesRepairedEvent {
+        from_id,
+        to_id,
+        issues_fixed,
+    };
+
+    publish(
+        env,
+        "indexes_repaired",
+        "admin",
+        EntityId::Account(admin.clone()),
+        Some(admin),
+        EventPayload::IndexesRepaired(event),
+    );
+}
+
+/// Emit a GuardianSet event when the admin appoints a guardian
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin who appointed the guardian
+/// * `guardian` - New guardian address
+pub fn emit_guardian_set(env: &Env, admin: &Address, guardian: &Address) {
+    let event = GuardianSetEvent {
+        guardian: guardian.clone(),
+    };
+
+    publish(
+        env,
+        "guardian_set",
+        "admin",
+        EntityId::Account(guardian.clone()),
+        Some(admin),
+        EventPayload::GuardianSet(event),
+    );
 }
 
-/// Emit a request completed event
-#[allow(dead_code)]
-pub fn emit_request_completed(env: &Env, request_id: u64) {
-    emit_status_changed(
+/// Emit a RateLimitSet event when the admin sets a hospital's request limits
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `admin` - Admin who set the limits
+/// * `hospital_id` - Hospital the limits apply to
+/// * `limit` - New limits
+pub fn emit_rate_limit_set(env: &Env, admin: &Address, hospital_id: &Address, limit: RateLimit) {
+    let event = RateLimitSetEvent {
+        hospital_id: hospital_id.clone(),
+        limit,
+    };
+
+    publish(
         env,
+        "rate_limit_set",
+        "hospital",
+        EntityId::Account(hospital_id.clone()),
+        Some(admin),
+        EventPayload::RateLimitSet(event),
+    );
+}
+
+/// Emit a RequestOverLimit event when a critical request exceeds its hospital's limits
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `request_id` - Request flagged for review
+/// * `hospital_id` - Hospital that created the request
+/// * `window_requests` - Requests in the current window, including this one
+/// * `open_requests` - Open requests, including this one
+pub fn emit_request_over_limit(
+    env: &Env,
+    request_id: u64,
+    hospital_id: &Address,
+    window_requests: u32,
+    open_requests: u32,
+) {
+    let event = RequestOverLimitEvent {
         request_id,
-        RequestStatus::InDelivery,
-        RequestStatus::Completed,
+        hospital_id: hospital_id.clone(),
+        window_requests,
+        open_requests,
+    };
+
+    publish(
+        env,
+        "request_over_limit",
+        "request",
+        EntityId::Record(request_id),
+        Some(hospital_id),
+        EventPayload::RequestOverLimit(event),
+    );
+}
+
+/// Emit a PauseChanged event when an operation family is paused or unpaused
+///
+/// # Arguments
+/// * `env` - Contract environment
+/// * `caller` - Admin or guardian who made the change
+/// * `scope` - Operation family the change applies to
+/// * `paused` - Whether the family is now paused
+/// * `reason` - Why the pause was raised or lifted
+pub fn emit_pause_changed(
+    env: &Env,
+    caller: &Address,
+    scope: PauseScope,
+    paused: bool,
+    reason: String,
+) {
+    let event = PauseChangedEvent {
+        scope,
+        paused,
+        reason,
+    };
+
+    publish(
+        env,
+        if paused { "paused" } else { "unpaused" },
+        "contract",
+        EntityId::Account(env.current_contract_address()),
+        Some(caller),
+        EventPayload::PauseChanged(event),
     );
 }
@@ -0,0 +1,318 @@
+use crate::storage;
+use crate::types::{
+    BloodType, DataKey, IndexIssue, IndexKind, InvariantReport, IssueKind, RequestStatus,
+    UrgencyLevel,
+};
+use soroban_sdk::{Env, Map, Vec};
+
+const BLOOD_TYPES: [BloodType; 8] = [
+    BloodType::APositive,
+    BloodType::ANegative,
+    BloodType::BPositive,
+    BloodType::BNegative,
+    BloodType::ABPositive,
+    BloodType::ABNegative,
+    BloodType::OPositive,
+    BloodType::ONegative,
+];
+
+const STATUSES: [RequestStatus; 7] = [
+    RequestStatus::Pending,
+    RequestStatus::Approved,
+    RequestStatus::Fulfilled,
+    RequestStatus::InDelivery,
+    RequestStatus::Completed,
+    RequestStatus::Cancelled,
+    RequestStatus::Expired,
+];
+
+const URGENCIES: [UrgencyLevel; 3] = [
+    UrgencyLevel::Critical,
+    UrgencyLevel::Urgent,
+    UrgencyLevel::Normal,
+];
+
+/// Issues found on one page
+struct Checker<'a> {
+    env: &'a Env,
+    issues: Vec<IndexIssue>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, index: IndexKind, kind: IssueKind, request_id: u64) {
+        self.report_counter(index, kind, request_id, 0, 0);
+    }
+
+    fn report_counter(
+        &mut self,
+        index: IndexKind,
+        kind: IssueKind,
+        request_id: u64,
+        expected: u64,
+        actual: u64,
+    ) {
+        self.issues.push_back(IndexIssue {
+            index,
+            kind,
+            request_id,
+            expected,
+            actual,
+        });
+    }
+}
+
+/// In-memory copy of one index, normalised to ascending order
+///
+/// Changes are only written back when repairing.
+struct Index {
+    kind: IndexKind,
+    key: DataKey,
+    ids: Vec<u64>,
+    dirty: bool,
+}
+
+impl Index {
+    fn load(checker: &mut Checker, kind: IndexKind, key: DataKey) -> Index {
+        let stored = storage::get_index(checker.env, &key);
+
+        let mut sorted = true;
+        for i in 1..stored.len() {
+            if stored.get_unchecked(i - 1) >= stored.get_unchecked(i) {
+                sorted = false;
+                break;
+            }
+        }
+
+        let ids = if sorted {
+            stored
+        } else {
+            checker.report(kind, IssueKind::Unsorted, 0);
+            let mut ids = Vec::new(checker.env);
+            for id in stored.iter() {
+                storage::insert_sorted(&mut ids, id);
+            }
+            ids
+        };
+
+        Index {
+            kind,
+            key,
+            ids,
+            dirty: !sorted,
+        }
+    }
+
+    /// Check that `id` is listed exactly when it `belongs` here
+    fn expect(&mut self, checker: &mut Checker, id: u64, belongs: bool) {
+        match (belongs, self.ids.binary_search(id)) {
+            (true, Err(pos)) => {
+                checker.report(self.kind, IssueKind::MissingEntry, id);
+                self.ids.insert(pos, id);
+                self.dirty = true;
+            }
+            (false, Ok(pos)) => {
+                checker.report(self.kind, IssueKind::StaleEntry, id);
+                self.ids.remove(pos);
+                self.dirty = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Drop entries for an ID that has no record
+    fn drop_dangling(&mut self, checker: &mut Checker, id: u64) {
+        if let Ok(pos) = self.ids.binary_search(id) {
+            checker.report(self.kind, IssueKind::DanglingEntry, id);
+            self.ids.remove(pos);
+            self.dirty = true;
+        }
+    }
+
+    /// Drop entries above the last issued request ID
+    fn drop_above(&mut self, checker: &mut Checker, last_id: u64) {
+        while let Some(id) = self.ids.last() {
+            if id <= last_id {
+                break;
+            }
+            checker.report(self.kind, IssueKind::DanglingEntry, id);
+            self.ids.pop_back();
+            self.dirty = true;
+        }
+    }
+
+    fn save(&self, env: &Env) {
+        if self.dirty {
+            storage::set_index(env, &self.key, &self.ids);
+        }
+    }
+}
+
+/// Hospital indexes, loaded on first use and keyed by storage key
+struct HospitalIndexes {
+    ids: Map<DataKey, Vec<u64>>,
+    dirty: Map<DataKey, bool>,
+}
+
+impl HospitalIndexes {
+    /// Check that the index under `key` lists `id`
+    fn expect(&mut self, checker: &mut Checker, key: DataKey, id: u64) {
+        let mut ids = match self.ids.get(key.clone()) {
+            Some(ids) => ids,
+            None => {
+                let index = Index::load(checker, IndexKind::Hospital, key.clone());
+                if index.dirty {
+                    self.dirty.set(key.clone(), true);
+                }
+                index.ids
+            }
+        };
+
+        if let Err(pos) = ids.binary_search(id) {
+            checker.report(IndexKind::Hospital, IssueKind::MissingEntry, id);
+            ids.insert(pos, id);
+            self.dirty.set(key.clone(), true);
+        }
+        self.ids.set(key, ids);
+    }
+
+    fn save(&self, env: &Env) {
+        for key in self.dirty.keys().iter() {
+            if let Some(ids) = self.ids.get(key.clone()) {
+                storage::set_index(env, &key, &ids);
+            }
+        }
+    }
+}
+
+/// Check one page of blood requests, fixing what is found when `repair` is set
+///
+/// Each request must be listed in the hospital, blood type, status and
+/// urgency index matching its record and in no other blood type, status or
+/// urgency index. Closed requests must not be flagged as overdue; open ones
+/// are flagged by `escalate_requests`, so a missing flag is not an issue.
+pub fn check(env: &Env, cursor: u64, limit: u32, repair: bool) -> InvariantReport {
+    let mut checker = Checker {
+        env,
+        issues: Vec::new(env),
+    };
+
+    let mut type_indexes = BLOOD_TYPES.map(|blood_type| {
+        Index::load(
+            &mut checker,
+            IndexKind::BloodType,
+            DataKey::BloodTypeIndex(blood_type),
+        )
+    });
+    let mut status_indexes = STATUSES.map(|status| {
+        Index::load(
+            &mut checker,
+            IndexKind::Status,
+            DataKey::StatusIndex(status),
+        )
+    });
+    let mut urgency_indexes = URGENCIES.map(|urgency| {
+        Index::load(
+            &mut checker,
+            IndexKind::Urgency,
+            DataKey::UrgencyIndex(urgency),
+        )
+    });
+    let mut overdue_index = Index::load(&mut checker, IndexKind::Overdue, DataKey::OverdueIndex);
+    let mut hospital_indexes = HospitalIndexes {
+        ids: Map::new(env),
+        dirty: Map::new(env),
+    };
+
+    let counter = storage::get_request_counter(env);
+    let mut request_id = cursor.max(1);
+    let mut scanned = 0u32;
+
+    while request_id <= counter && scanned < limit {
+        match storage::get_blood_request(env, request_id) {
+            None => {
+                checker.report(IndexKind::Record, IssueKind::MissingRecord, request_id);
+                for index in type_indexes
+                    .iter_mut()
+                    .chain(status_indexes.iter_mut())
+                    .chain(urgency_indexes.iter_mut())
+                {
+                    index.drop_dangling(&mut checker, request_id);
+                }
+                overdue_index.drop_dangling(&mut checker, request_id);
+            }
+            Some(request) => {
+                for (blood_type, index) in BLOOD_TYPES.iter().zip(type_indexes.iter_mut()) {
+                    index.expect(&mut checker, request_id, *blood_type == request.blood_type);
+                }
+                for (status, index) in STATUSES.iter().zip(status_indexes.iter_mut()) {
+                    index.expect(&mut checker, request_id, *status == request.status);
+                }
+                for (urgency, index) in URGENCIES.iter().zip(urgency_indexes.iter_mut()) {
+                    index.expect(&mut checker, request_id, *urgency == request.urgency);
+                }
+
+                let is_open = request.status == RequestStatus::Pending
+                    || request.status == RequestStatus::Approved;
+                if !is_open {
+                    overdue_index.expect(&mut checker, request_id, false);
+                }
+
+                hospital_indexes.expect(
+                    &mut checker,
+                    DataKey::HospitalIndex(request.hospital_id),
+                    request_id,
+                );
+            }
+        }
+
+        request_id += 1;
+        scanned += 1;
+    }
+
+    let done = request_id > counter;
+    if done {
+        // Requests stored past a lagging counter would be overwritten
+        let mut last_id = counter;
+        while storage::get_blood_request(env, last_id + 1).is_some() {
+            last_id += 1;
+        }
+        if last_id != counter {
+            checker.report_counter(
+                IndexKind::RequestCounter,
+                IssueKind::CounterMismatch,
+                0,
+                last_id,
+                counter,
+            );
+            if repair {
+                storage::set_request_counter(env, last_id);
+            }
+        }
+
+        for index in type_indexes
+            .iter_mut()
+            .chain(status_indexes.iter_mut())
+            .chain(urgency_indexes.iter_mut())
+        {
+            index.drop_above(&mut checker, last_id);
+        }
+        overdue_index.drop_above(&mut checker, last_id);
+    }
+
+    if repair {
+        for index in type_indexes
+            .iter()
+            .chain(status_indexes.iter())
+            .chain(urgency_indexes.iter())
+        {
+            index.save(env);
+        }
+        overdue_index.save(env);
+        hospital_indexes.save(env);
+    }
+
+    InvariantReport {
+        issues: checker.issues,
+        next_cursor: if done { 0 } else { request_id },
+    }
+}
@@ -1,25 +1,41 @@
 #![no_std]
+#![allow(clippy::too_many_arguments)]
 
 mod error;
 mod events;
+mod invariants;
 mod storage;
 mod types;
 mod validation;
 
 pub use crate::error::ContractError;
 pub use crate::types::{
-    BloodRequest, BloodType, DataKey, RequestCreatedEvent, RequestStatus,
-    RequestStatusChangedEvent, UnitsAssignedEvent, UrgencyLevel,
+    BloodOrder, BloodRequest, BloodType, DataKey, EntityId, EventEnvelope, EventPayload,
+    GuardianSetEvent, HospitalAuthorizationEvent, IndexIssue, IndexKind, IndexesRepairedEvent,
+    InitializedEvent, InvariantReport, IssueKind, OrderCreatedEvent, OrderLine, OrderLineRequest,
+    OrderLineUpdatedEvent, OrderPage, OrderStatus, PauseChangedEvent, PauseScope, ProductType,
+    RateLimit, RateLimitSetEvent, RequestCreatedEvent, RequestOverLimitEvent, RequestOverdueEvent,
+    RequestPage, RequestStatus, RequestStatusChangedEvent, RequestWindow, UnitsAssignedEvent,
+    UnitsReleasedEvent, UrgencyEscalatedEvent, UrgencyLevel, EVENT_SCHEMA_VERSION,
 };
 
-use soroban_sdk::{contract, contractimpl, Address, Env, Map, String, Vec};
-use crate::error::ContractError;
-use crate::types::{BloodRequest, BloodType, RequestMetadata, RequestStatus, UrgencyLevel};
+use soroban_sdk::{contract, contractimpl, Address, Env, Map, String, Symbol, Vec};
 
 // Pagination constants
 const DEFAULT_QUERY_LIMIT: u32 = 50;
 const MAX_QUERY_LIMIT: u32 = 200;
 
+// Deadline escalation windows (time remaining before required_by)
+const ESCALATE_TO_URGENT_SECONDS: u64 = 12 * 3600;
+const ESCALATE_TO_CRITICAL_SECONDS: u64 = 2 * 3600;
+
+// Multi-line order limits
+const MAX_ORDER_LINES: u32 = 20;
+const MAX_LINE_UNITS: u32 = 50;
+
+/// Maximum number of requests one `verify_invariants` or `repair_indexes` page scans
+const MAX_INVARIANT_SCAN: u32 = 100;
+
 #[contract]
 pub struct RequestContract;
 
@@ -44,6 +60,8 @@ impl RequestContract {
         // Set admin
         storage::set_admin(&env, &admin);
 
+        events::emit_initialized(&env, &admin);
+
         Ok(())
     }
 
@@ -67,6 +85,7 @@ impl RequestContract {
         admin.require_auth();
 
         storage::authorize_hospital(&env, &hospital);
+        events::emit_hospital_authorization(&env, &admin, &hospital, true);
 
         Ok(())
     }
@@ -89,6 +108,7 @@ impl RequestContract {
         admin.require_auth();
 
         storage::revoke_hospital(&env, &hospital);
+        events::emit_hospital_authorization(&env, &admin, &hospital, false);
 
         Ok(())
     }
@@ -131,6 +151,8 @@ impl RequestContract {
         procedure: String,
         notes: String,
     ) -> Result<u64, ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
         // 1. Verify hospital authentication
         hospital_id.require_auth();
 
@@ -145,25 +167,26 @@ impl RequestContract {
         }
 
         // 4. Validate request parameters
-        validation::validate_request_creation(&env, quantity_ml, required_by)?;
-        validation::validate_delivery_address(&delivery_address)?;
+        validation::validate_request_creation(&env, quantity_ml, required_by, &delivery_address)?;
         validation::validate_blood_type(&blood_type)?;
 
         // 5. Validate urgency-specific time window
         validation::validate_urgency_time_window(&env, required_by, urgency.priority_weight())?;
 
-        // 6. Generate request ID
+        // 6. Enforce the hospital's request limits; critical requests go through flagged
+        let over_limit = Self::count_against_rate_limit(&env, &hospital_id, urgency)?;
+
+        // 7. Generate request ID
         let request_id = storage::increment_request_id(&env);
         let current_time = env.ledger().timestamp();
 
-        // 7. Create request metadata
-        let metadata = RequestMetadata {
-            patient_id,
-            procedure,
-            notes,
-        };
+        // 8. Create request metadata
+        let mut metadata = Map::new(&env);
+        metadata.set(Symbol::new(&env, "patient_id"), patient_id.to_string());
+        metadata.set(Symbol::new(&env, "procedure"), procedure);
+        metadata.set(Symbol::new(&env, "notes"), notes);
 
-        // 8. Create blood request
+        // 9. Create blood request
         let request = BloodRequest {
             id: request_id,
             hospital_id: hospital_id.clone(),
@@ -176,22 +199,22 @@ impl RequestContract {
             fulfilled_at: None,
             assigned_units: Vec::new(&env),
             delivery_address,
-            metadata: Map::new(&env),
+            metadata,
         };
 
-        // 9. Validate the complete request
+        // 10. Validate the complete request
         request.validate(current_time)?;
 
-        // 10. Store request
+        // 11. Store request
         storage::set_blood_request(&env, &request);
 
-        // 11. Update indexes for efficient querying
+        // 12. Update indexes for efficient querying
         storage::add_to_hospital_index(&env, &request);
         storage::add_to_blood_type_index(&env, &request);
         storage::add_to_status_index(&env, &request);
         storage::add_to_urgency_index(&env, &request);
 
-        // 12. Emit event
+        // 13. Emit event
         events::emit_request_created(
             &env,
             request_id,
@@ -202,7 +225,19 @@ impl RequestContract {
             required_by,
         );
 
-        // 13. Return request ID
+        // 14. Flag a request over the limits for review
+        if let Some((window_requests, open_requests)) = over_limit {
+            storage::index_insert(&env, &DataKey::OverLimitIndex, request_id);
+            events::emit_request_over_limit(
+                &env,
+                request_id,
+                &hospital_id,
+                window_requests,
+                open_requests,
+            );
+        }
+
+        // 15. Return request ID
         Ok(request_id)
     }
 
@@ -216,9 +251,11 @@ impl RequestContract {
     /// Blood request details
     ///
     /// # Errors
-    /// - `RequestNotFound`: Request with given ID doesn't exist
+    /// - `NotFound`: Request with given ID doesn't exist
     pub fn get_request(env: Env, request_id: u64) -> Result<BloodRequest, ContractError> {
-        storage::get_blood_request(&env, request_id).ok_or(ContractError::RequestNotFound)
+        let request =
+            storage::get_blood_request(&env, request_id).ok_or(ContractError::NotFound)?;
+        Ok(Self::expire_if_overdue(&env, request))
     }
 
     /// Approve a pending blood request
@@ -232,6 +269,8 @@ impl RequestContract {
     /// - `NotFound`: Request doesn't exist
     /// - `InvalidStatusTransition`: Request is not in Pending status
     pub fn approve_request(env: Env, request_id: u64) -> Result<(), ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
         if !storage::is_initialized(&env) {
             return Err(ContractError::NotInitialized);
         }
@@ -261,7 +300,7 @@ impl RequestContract {
         storage::update_status_index(&env, request_id, old_status, RequestStatus::Approved);
 
         // Emit event
-        events::emit_request_approved(&env, request_id);
+        events::emit_request_approved(&env, request_id, &admin);
         
         Ok(())
     }
@@ -274,7 +313,7 @@ impl RequestContract {
     /// * `new_status` - New status for the request
     ///
     /// # Errors
-    /// - `RequestNotFound`: Request does not exist
+    /// - `NotFound`: Request does not exist
     /// - `InvalidStatusTransition`: Status transition is not allowed
     /// - `Unauthorized`: Caller is not authorized
     pub fn update_request_status(
@@ -282,12 +321,14 @@ impl RequestContract {
         request_id: u64,
         new_status: RequestStatus,
     ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
         let admin = storage::get_admin(&env);
         admin.require_auth();
 
         // Get existing request
         let mut request = storage::get_blood_request(&env, request_id)
-            .ok_or(ContractError::RequestNotFound)?;
+            .ok_or(ContractError::NotFound)?;
 
         // Validate status transition
         if !request.status.can_transition_to(&new_status) {
@@ -306,7 +347,13 @@ impl RequestContract {
         storage::set_blood_request(&env, &request);
 
         // Emit event
-        events::emit_request_status_changed(&env, request_id, old_status, new_status);
+        events::emit_request_status_changed(
+            &env,
+            request_id,
+            old_status,
+            new_status,
+            Some(&admin),
+        );
 
         Ok(())
     }
@@ -325,6 +372,8 @@ impl RequestContract {
     /// - `CannotCancelRequest`: Request cannot be cancelled in current state
     /// - `Unauthorized`: Caller is not hospital owner or admin
     pub fn cancel_request(env: Env, request_id: u64, caller: Address) -> Result<(), ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
         caller.require_auth();
 
         if !storage::is_initialized(&env) {
@@ -355,7 +404,7 @@ impl RequestContract {
         storage::update_status_index(&env, request_id, old_status, RequestStatus::Cancelled);
 
         // Emit event
-        events::emit_request_cancelled(&env, request_id, old_status);
+        events::emit_request_cancelled(&env, request_id, old_status, &caller);
 
         Ok(())
     }
@@ -368,19 +417,21 @@ impl RequestContract {
     /// * `unit_ids` - Vector of blood unit IDs to assign
     ///
     /// # Errors
-    /// - `RequestNotFound`: Request does not exist
+    /// - `NotFound`: Request does not exist
     /// - `Unauthorized`: Caller is not authorized
     pub fn assign_blood_units(
         env: Env,
         request_id: u64,
         unit_ids: soroban_sdk::Vec<u64>,
     ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env, PauseScope::Allocation)?;
+
         let admin = storage::get_admin(&env);
         admin.require_auth();
 
         // Get existing request
         let mut request = storage::get_blood_request(&env, request_id)
-            .ok_or(ContractError::RequestNotFound)?;
+            .ok_or(ContractError::NotFound)?;
 
         // Assign units
         request.assigned_units = unit_ids.clone();
@@ -389,7 +440,7 @@ impl RequestContract {
         storage::set_blood_request(&env, &request);
 
         // Emit event
-        events::emit_units_assigned(&env, request_id, unit_ids);
+        events::emit_units_assigned(&env, request_id, unit_ids, &admin);
 
         Ok(())
     }
@@ -454,6 +505,631 @@ impl RequestContract {
         storage::is_authorized_hospital(&env, &hospital)
     }
 
+    /// Escalate urgency of open requests as their deadline approaches
+    ///
+    /// Keeper-callable; anyone may run it. Scans up to `limit` requests by ID
+    /// starting at `cursor`. Pending and Approved requests move to Urgent when
+    /// less than 12 hours remain and to Critical when less than 2 hours remain.
+    /// Urgency is never lowered. Requests past `required_by` are added to the
+    /// overdue index once.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First request ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (1-200)
+    ///
+    /// # Returns
+    /// Cursor to resume from, or 0 once all requests have been scanned
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    ///
+    /// # Events
+    /// Emits `urgency_escalated` and `request_overdue` events
+    pub fn escalate_requests(env: Env, cursor: u64, limit: u32) -> Result<u64, ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if limit == 0 || limit > MAX_QUERY_LIMIT {
+            return Err(ContractError::InvalidInput);
+        }
+
+        let current_time = env.ledger().timestamp();
+        let last_id = storage::get_request_counter(&env);
+        let mut request_id = cursor.max(1);
+        let mut scanned = 0u32;
+
+        while request_id <= last_id {
+            if scanned >= limit {
+                return Ok(request_id);
+            }
+            scanned += 1;
+
+            if let Some(request) = storage::get_blood_request(&env, request_id) {
+                Self::escalate_request(&env, request, current_time);
+            }
+
+            request_id += 1;
+        }
+
+        Ok(0)
+    }
+
+    /// Expire open requests whose `required_by` has passed
+    ///
+    /// Keeper-callable; anyone may run it. Scans up to `limit` requests by ID
+    /// starting at `cursor`. Pending and Approved requests past their deadline
+    /// move to Expired and any assigned units are released. Reads through
+    /// `get_request` apply the same expiry lazily.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First request ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (1-200)
+    ///
+    /// # Returns
+    /// Cursor to resume from, or 0 once all requests have been scanned
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    ///
+    /// # Events
+    /// Emits `request_status_changed` for each expired request and
+    /// `units_released` when assigned units are released
+    pub fn expire_requests(env: Env, cursor: u64, limit: u32) -> Result<u64, ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if limit == 0 || limit > MAX_QUERY_LIMIT {
+            return Err(ContractError::InvalidInput);
+        }
+
+        let last_id = storage::get_request_counter(&env);
+        let mut request_id = cursor.max(1);
+        let mut scanned = 0u32;
+
+        while request_id <= last_id {
+            if scanned >= limit {
+                return Ok(request_id);
+            }
+            scanned += 1;
+
+            if let Some(request) = storage::get_blood_request(&env, request_id) {
+                Self::expire_if_overdue(&env, request);
+            }
+
+            request_id += 1;
+        }
+
+        Ok(0)
+    }
+
+    /// Appoint the guardian, who may pause operations alongside the admin
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `guardian` - Guardian address, replacing any previous one
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    ///
+    /// # Events
+    /// Emits `guardian_set`
+    pub fn set_guardian(env: Env, guardian: Address) -> Result<(), ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let admin = storage::get_admin(&env);
+        admin.require_auth();
+
+        storage::set_guardian(&env, &guardian);
+        events::emit_guardian_set(&env, &admin, &guardian);
+
+        Ok(())
+    }
+
+    /// Get the guardian, if one has been appointed
+    pub fn get_guardian(env: Env) -> Option<Address> {
+        storage::get_guardian(&env)
+    }
+
+    /// Pause an operation family, or every family with `PauseScope::All`
+    ///
+    /// Paused operations fail with `ContractPaused`; queries and
+    /// `expire_requests` keep working.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `caller` - Admin or guardian address
+    /// * `scope` - Operation family to pause
+    /// * `reason` - Why the pause is raised
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `Unauthorized`: Caller is neither the admin nor the guardian
+    ///
+    /// # Events
+    /// Emits `paused`
+    pub fn pause(
+        env: Env,
+        caller: Address,
+        scope: PauseScope,
+        reason: String,
+    ) -> Result<(), ContractError> {
+        caller.require_auth();
+
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let is_guardian = storage::get_guardian(&env).is_some_and(|guardian| guardian == caller);
+        if caller != storage::get_admin(&env) && !is_guardian {
+            return Err(ContractError::Unauthorized);
+        }
+
+        storage::set_paused(&env, scope, true);
+        events::emit_pause_changed(&env, &caller, scope, true, reason);
+
+        Ok(())
+    }
+
+    /// Lift the pause on an operation family
+    ///
+    /// Only the admin can unpause, so a compromised guardian key cannot undo
+    /// a pause. Unpausing `PauseScope::All` leaves families paused on their
+    /// own still paused.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `caller` - Admin address
+    /// * `scope` - Operation family to unpause
+    /// * `reason` - Why the pause is lifted
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `Unauthorized`: Caller is not the admin
+    ///
+    /// # Events
+    /// Emits `unpaused`
+    pub fn unpause(
+        env: Env,
+        caller: Address,
+        scope: PauseScope,
+        reason: String,
+    ) -> Result<(), ContractError> {
+        caller.require_auth();
+
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if caller != storage::get_admin(&env) {
+            return Err(ContractError::Unauthorized);
+        }
+
+        storage::set_paused(&env, scope, false);
+        events::emit_pause_changed(&env, &caller, scope, false, reason);
+
+        Ok(())
+    }
+
+    /// Check whether an operation family is paused, on its own or by `PauseScope::All`
+    pub fn is_paused(env: Env, scope: PauseScope) -> bool {
+        storage::is_paused(&env, scope)
+    }
+
+    /// Set the request limits for a hospital
+    ///
+    /// A zero field disables that limit. Setting limits starts a fresh window.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `hospital` - Hospital the limits apply to
+    /// * `limit` - Requests per window and maximum open requests
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: A request count is set without a window length
+    ///
+    /// # Events
+    /// Emits `rate_limit_set`
+    pub fn set_rate_limit(
+        env: Env,
+        hospital: Address,
+        limit: RateLimit,
+    ) -> Result<(), ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let admin = storage::get_admin(&env);
+        admin.require_auth();
+
+        if limit.max_requests > 0 && limit.window_secs == 0 {
+            return Err(ContractError::InvalidInput);
+        }
+
+        storage::set_rate_limit(&env, &hospital, &limit);
+        events::emit_rate_limit_set(&env, &admin, &hospital, limit);
+
+        Ok(())
+    }
+
+    /// Get the request limits for a hospital, if any are set
+    pub fn get_rate_limit(env: Env, hospital: Address) -> Option<RateLimit> {
+        storage::get_rate_limit(&env, &hospital)
+    }
+
+    /// Get IDs of critical requests created over their hospital's limits, pending review
+    pub fn get_over_limit_requests(env: Env) -> Vec<u64> {
+        storage::get_index(&env, &DataKey::OverLimitIndex)
+    }
+
+    /// Clear the review flag on an over-limit request
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `request_id` - Flagged request
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `NotFound`: Request is not flagged
+    pub fn clear_over_limit_flag(env: Env, request_id: u64) -> Result<(), ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        storage::get_admin(&env).require_auth();
+
+        if !storage::index_contains(&env, &DataKey::OverLimitIndex, request_id) {
+            return Err(ContractError::NotFound);
+        }
+        storage::index_remove(&env, &DataKey::OverLimitIndex, request_id);
+
+        Ok(())
+    }
+
+    /// Check one page of blood requests against their indexes and counters
+    ///
+    /// Read-only. Scans up to `limit` request IDs from `cursor` and reports
+    /// requests missing from an index they belong in, index entries under the
+    /// wrong key or without a record, closed requests still flagged overdue and
+    /// unsorted indexes. Entries beyond the request counter and a counter
+    /// lagging behind the records are checked on the last page.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First request ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (1-100)
+    ///
+    /// # Returns
+    /// Issues found and the cursor to resume from (0 once all requests are checked)
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    pub fn verify_invariants(
+        env: Env,
+        cursor: u64,
+        limit: u32,
+    ) -> Result<InvariantReport, ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if limit == 0 || limit > MAX_INVARIANT_SCAN {
+            return Err(ContractError::InvalidInput);
+        }
+
+        Ok(invariants::check(&env, cursor, limit, false))
+    }
+
+    /// Fix what `verify_invariants` reports for one page of blood requests
+    ///
+    /// Missing entries are added, stale and dangling entries removed, indexes
+    /// re-sorted and the request counter reset. Only callable by the admin.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First request ID to scan (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (1-100)
+    ///
+    /// # Returns
+    /// Issues fixed and the cursor to resume from (0 once all requests are checked)
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `InvalidInput`: Limit is zero or above the maximum
+    ///
+    /// # Events
+    /// Emits `indexes_repaired` when anything was fixed
+    pub fn repair_indexes(
+        env: Env,
+        cursor: u64,
+        limit: u32,
+    ) -> Result<InvariantReport, ContractError> {
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        let admin = storage::get_admin(&env);
+        admin.require_auth();
+
+        if limit == 0 || limit > MAX_INVARIANT_SCAN {
+            return Err(ContractError::InvalidInput);
+        }
+
+        let report = invariants::check(&env, cursor, limit, true);
+        if !report.issues.is_empty() {
+            let from_id = cursor.max(1);
+            let to_id = if report.next_cursor == 0 {
+                storage::get_request_counter(&env)
+            } else {
+                report.next_cursor - 1
+            };
+            events::emit_indexes_repaired(&env, &admin, from_id, to_id, report.issues.len());
+        }
+
+        Ok(report)
+    }
+
+    /// Create a multi-line blood order
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `hospital_id` - Hospital's address (must be authorized)
+    /// * `lines` - Line items, each with product, blood type and unit count
+    /// * `urgency` - Urgency level shared by all lines
+    /// * `required_by` - Unix timestamp when blood is required
+    /// * `delivery_address` - Delivery address for the blood units
+    ///
+    /// # Returns
+    /// Unique ID of the created order
+    ///
+    /// # Errors
+    /// - `NotInitialized`: Contract not initialized
+    /// - `NotAuthorizedHospital`: Hospital is not authorized
+    /// - `InvalidOrderLine`: No lines or more than 20 lines
+    /// - `InvalidQuantity`: A line asks for 0 or more than 50 units
+    /// - `InvalidRequiredBy`: Deadline outside the allowed window
+    /// - `InvalidDeliveryAddress`: Empty delivery address
+    ///
+    /// # Events
+    /// Emits `order_created`
+    pub fn create_order(
+        env: Env,
+        hospital_id: Address,
+        lines: Vec<OrderLineRequest>,
+        urgency: UrgencyLevel,
+        required_by: u64,
+        delivery_address: String,
+    ) -> Result<u64, ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
+        hospital_id.require_auth();
+
+        if !storage::is_initialized(&env) {
+            return Err(ContractError::NotInitialized);
+        }
+
+        if !storage::is_authorized_hospital(&env, &hospital_id) {
+            return Err(ContractError::NotAuthorizedHospital);
+        }
+
+        if lines.is_empty() || lines.len() > MAX_ORDER_LINES {
+            return Err(ContractError::InvalidOrderLine);
+        }
+
+        validation::validate_delivery_address(&delivery_address)?;
+        validation::validate_urgency_time_window(&env, required_by, urgency.priority_weight())?;
+
+        let mut order_lines = Vec::new(&env);
+        for line in lines.iter() {
+            if line.quantity_units == 0 || line.quantity_units > MAX_LINE_UNITS {
+                return Err(ContractError::InvalidQuantity);
+            }
+            validation::validate_blood_type(&line.blood_type)?;
+
+            order_lines.push_back(OrderLine {
+                product: line.product,
+                blood_type: line.blood_type,
+                quantity_units: line.quantity_units,
+                assigned_units: Vec::new(&env),
+                status: OrderStatus::Open,
+            });
+        }
+
+        let order = BloodOrder {
+            id: storage::increment_order_id(&env),
+            hospital_id: hospital_id.clone(),
+            urgency,
+            created_at: env.ledger().timestamp(),
+            required_by,
+            delivery_address,
+            lines: order_lines,
+            status: OrderStatus::Open,
+        };
+
+        storage::set_blood_order(&env, &order);
+        storage::add_to_hospital_order_index(&env, &order);
+
+        events::emit_order_created(
+            &env,
+            order.id,
+            &hospital_id,
+            lines.len(),
+            urgency,
+            required_by,
+        );
+
+        Ok(order.id)
+    }
+
+    /// Assign blood units to one line of an order
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `order_id` - ID of the order
+    /// * `line_index` - Index of the line within the order
+    /// * `unit_ids` - Blood unit IDs to add to the line
+    ///
+    /// # Returns
+    /// Derived status of the whole order
+    ///
+    /// # Errors
+    /// - `OrderNotFound`: Order doesn't exist
+    /// - `InvalidOrderLine`: Line index out of range
+    /// - `InvalidStatusTransition`: Line is already fulfilled or cancelled
+    /// - `InsufficientQuantity`: More units than the line still needs
+    pub fn assign_order_units(
+        env: Env,
+        order_id: u64,
+        line_index: u32,
+        unit_ids: Vec<u64>,
+    ) -> Result<OrderStatus, ContractError> {
+        Self::require_not_paused(&env, PauseScope::Allocation)?;
+
+        let admin = storage::get_admin(&env);
+        admin.require_auth();
+
+        let mut order =
+            storage::get_blood_order(&env, order_id).ok_or(ContractError::OrderNotFound)?;
+        let mut line = order
+            .lines
+            .get(line_index)
+            .ok_or(ContractError::InvalidOrderLine)?;
+
+        if line.status == OrderStatus::Fulfilled || line.status == OrderStatus::Cancelled {
+            return Err(ContractError::InvalidStatusTransition);
+        }
+
+        if unit_ids.is_empty()
+            || line.assigned_units.len() + unit_ids.len() > line.quantity_units
+        {
+            return Err(ContractError::InsufficientQuantity);
+        }
+
+        line.assigned_units.append(&unit_ids);
+        line.status = line.fill_status();
+        let line_status = line.status;
+
+        order.lines.set(line_index, line);
+        order.status = order.derive_status();
+        storage::set_blood_order(&env, &order);
+
+        events::emit_order_line_updated(
+            &env,
+            order_id,
+            line_index,
+            line_status,
+            order.status,
+            &admin,
+        );
+
+        Ok(order.status)
+    }
+
+    /// Cancel the unfulfilled lines of an order
+    ///
+    /// Fulfilled lines are kept; if none are, the whole order becomes Cancelled.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `order_id` - ID of the order
+    /// * `caller` - Address of the caller (hospital or admin)
+    ///
+    /// # Errors
+    /// - `OrderNotFound`: Order doesn't exist
+    /// - `Unauthorized`: Caller is not hospital owner or admin
+    /// - `CannotCancelRequest`: Order is already fulfilled or cancelled
+    pub fn cancel_order(env: Env, order_id: u64, caller: Address) -> Result<(), ContractError> {
+        Self::require_not_paused(&env, PauseScope::Requests)?;
+
+        caller.require_auth();
+
+        let mut order =
+            storage::get_blood_order(&env, order_id).ok_or(ContractError::OrderNotFound)?;
+
+        let admin = storage::get_admin(&env);
+        if caller != order.hospital_id && caller != admin {
+            return Err(ContractError::Unauthorized);
+        }
+
+        if order.status == OrderStatus::Fulfilled || order.status == OrderStatus::Cancelled {
+            return Err(ContractError::CannotCancelRequest);
+        }
+
+        let mut lines = Vec::new(&env);
+        let mut cancelled = Vec::new(&env);
+        for (index, mut line) in order.lines.iter().enumerate() {
+            if line.status != OrderStatus::Fulfilled {
+                line.status = OrderStatus::Cancelled;
+                cancelled.push_back(index as u32);
+            }
+            lines.push_back(line);
+        }
+
+        order.lines = lines;
+        order.status = order.derive_status();
+        storage::set_blood_order(&env, &order);
+
+        for index in cancelled.iter() {
+            events::emit_order_line_updated(
+                &env,
+                order_id,
+                index,
+                OrderStatus::Cancelled,
+                order.status,
+                &caller,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Get blood order details by ID
+    ///
+    /// # Errors
+    /// - `OrderNotFound`: Order with given ID doesn't exist
+    pub fn get_order(env: Env, order_id: u64) -> Result<BloodOrder, ContractError> {
+        storage::get_blood_order(&env, order_id).ok_or(ContractError::OrderNotFound)
+    }
+
+    /// Get all order IDs for a specific hospital
+    pub fn get_hospital_orders(env: Env, hospital: Address) -> Vec<u64> {
+        storage::get_orders_by_hospital(&env, &hospital)
+    }
+
+    /// Get IDs of requests flagged as overdue
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    ///
+    /// # Returns
+    /// Vector of request IDs
+    pub fn get_overdue_requests(env: Env) -> Vec<u64> {
+        storage::get_overdue_requests(&env)
+    }
+
+    /// Check if a request has been flagged as overdue
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `request_id` - ID of the request
+    ///
+    /// # Returns
+    /// true if flagged overdue, false otherwise
+    pub fn is_request_overdue(env: Env, request_id: u64) -> bool {
+        storage::is_overdue(&env, request_id)
+    }
+
     // ========== Advanced Query Functions ==========
 
     /// Get a blood request by ID
@@ -466,6 +1142,7 @@ impl RequestContract {
     /// Option containing the blood request if found, None otherwise
     pub fn get_request_by_id(env: Env, request_id: u64) -> Option<BloodRequest> {
         storage::get_blood_request(&env, request_id)
+            .map(|request| Self::expire_if_overdue(&env, request))
     }
 
     /// Query hospital requests with optional status filtering and pagination
@@ -494,9 +1171,7 @@ impl RequestContract {
         
         // Apply status filter if provided
         if let Some(status) = status_filter {
-            requests = requests.into_iter()
-                .filter(|r| r.status == status)
-                .collect();
+            let mut f = Vec::new(&env); for r in requests.iter() { if r.status == status { f.push_back(r); } } requests = f;
         }
         
         // Apply pagination
@@ -520,8 +1195,13 @@ impl RequestContract {
         // Get all pending request IDs
         let request_ids = storage::get_requests_by_status(&env, RequestStatus::Pending);
         
-        // Load full request objects
-        let mut requests = Self::load_requests_from_ids(&env, request_ids);
+        // Load full request objects, dropping any that just expired
+        let mut requests = Vec::new(&env);
+        for request in Self::load_requests_from_ids(&env, request_ids).iter() {
+            if request.status == RequestStatus::Pending {
+                requests.push_back(request);
+            }
+        }
         
         // Sort by urgency (Critical > Urgent > Normal)
         Self::sort_requests_by_urgency(&mut requests);
@@ -575,10 +1255,7 @@ impl RequestContract {
         };
         
         // Load requests and filter by date range
-        let requests = Self::load_requests_from_ids(&env, request_ids)
-            .into_iter()
-            .filter(|r| r.created_at >= start_time && r.created_at <= end_time)
-            .collect();
+        let mut requests = Vec::new(&env); for r in Self::load_requests_from_ids(&env, request_ids).iter() { if r.created_at >= start_time && r.created_at <= end_time { requests.push_back(r); } }
         
         // Apply pagination
         Self::apply_pagination(requests, limit, offset)
@@ -595,7 +1272,7 @@ impl RequestContract {
     ///
     /// # Returns
     /// Vector of requests matching the urgency and optional status
-    pub fn query_requests_by_urgency_and_status(
+    pub fn query_by_urgency_and_status(
         env: Env,
         urgency: UrgencyLevel,
         status_filter: Option<RequestStatus>,
@@ -610,23 +1287,403 @@ impl RequestContract {
         
         // Apply status filter if provided
         if let Some(status) = status_filter {
-            requests = requests.into_iter()
-                .filter(|r| r.status == status)
-                .collect();
+            let mut f = Vec::new(&env); for r in requests.iter() { if r.status == status { f.push_back(r); } } requests = f;
         }
         
         // Apply pagination
         Self::apply_pagination(requests, limit, offset)
     }
 
+    // ========== Cursor Pagination ==========
+    //
+    // Each page call reads the bucket list of one index, the buckets the page
+    // spans and at most `limit` records, however many requests match. Pass 0
+    // as the first cursor and the returned `next_cursor` afterwards; 0 means
+    // there are no more pages. Filters are applied to the scanned records, so
+    // a filtered page can hold fewer than `limit` entries while more pages
+    // remain.
+
+    /// Page through a hospital's requests with optional status filtering
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `hospital_id` - Hospital address to query
+    /// * `status_filter` - Optional status filter (None returns all statuses)
+    /// * `cursor` - First request ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (defaults to 50, max 200)
+    pub fn page_hospital_requests(
+        env: Env,
+        hospital_id: Address,
+        status_filter: Option<RequestStatus>,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> RequestPage {
+        let key = DataKey::HospitalIndex(hospital_id);
+        Self::load_request_page(&env, &key, cursor, limit, status_filter)
+    }
+
+    /// Page through requests with a specific status
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `status` - Request status to filter by
+    /// * `cursor` - First request ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests (defaults to 50, max 200)
+    pub fn page_requests_by_status(
+        env: Env,
+        status: RequestStatus,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> RequestPage {
+        let key = DataKey::StatusIndex(status);
+        Self::load_request_page(&env, &key, cursor, limit, Some(status))
+    }
+
+    /// Page through requests for a specific blood type
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `blood_type` - Blood type to filter by
+    /// * `status_filter` - Optional status filter
+    /// * `cursor` - First request ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (defaults to 50, max 200)
+    pub fn page_requests_by_blood_type(
+        env: Env,
+        blood_type: BloodType,
+        status_filter: Option<RequestStatus>,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> RequestPage {
+        let key = DataKey::BloodTypeIndex(blood_type);
+        Self::load_request_page(&env, &key, cursor, limit, status_filter)
+    }
+
+    /// Page through requests with a specific urgency level
+    ///
+    /// Pending requests in urgency order can be read by paging Critical,
+    /// then Urgent, then Normal with a `Pending` status filter.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `urgency` - Urgency level to filter by
+    /// * `status_filter` - Optional status filter
+    /// * `cursor` - First request ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests to scan (defaults to 50, max 200)
+    pub fn page_requests_by_urgency(
+        env: Env,
+        urgency: UrgencyLevel,
+        status_filter: Option<RequestStatus>,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> RequestPage {
+        let key = DataKey::UrgencyIndex(urgency);
+        Self::load_request_page(&env, &key, cursor, limit, status_filter)
+    }
+
+    /// Page through requests created within a time range
+    ///
+    /// Request IDs are issued in creation order, so the first page is located
+    /// with a binary search over IDs rather than a scan.
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `start_time` - Start of time range (unix timestamp)
+    /// * `end_time` - End of time range (unix timestamp)
+    /// * `status_filter` - Optional status filter
+    /// * `cursor` - First request ID to include (0 starts at `start_time`)
+    /// * `limit` - Maximum number of requests to scan (defaults to 50, max 200)
+    pub fn page_requests_by_date_range(
+        env: Env,
+        start_time: u64,
+        end_time: u64,
+        status_filter: Option<RequestStatus>,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> RequestPage {
+        let last_id = storage::get_request_counter(&env);
+
+        // Find the first request created at or after start_time
+        let mut low = 1u64;
+        let mut high = last_id + 1;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let created_at = storage::get_blood_request(&env, mid)
+                .map(|request| request.created_at)
+                .unwrap_or(0);
+            if created_at < start_time {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        let mut request_id = cursor.max(low);
+        let mut requests = Vec::new(&env);
+        let mut scanned = 0u32;
+        let limit = Self::page_limit(limit);
+
+        while request_id <= last_id && scanned < limit {
+            let Some(request) = storage::get_blood_request(&env, request_id) else {
+                request_id += 1;
+                continue;
+            };
+            if request.created_at > end_time {
+                return RequestPage {
+                    requests,
+                    next_cursor: 0,
+                };
+            }
+
+            let request = Self::expire_if_overdue(&env, request);
+            if status_filter.is_none() || status_filter == Some(request.status) {
+                requests.push_back(request);
+            }
+            scanned += 1;
+            request_id += 1;
+        }
+
+        let more = request_id <= last_id
+            && storage::get_blood_request(&env, request_id)
+                .map(|request| request.created_at <= end_time)
+                .unwrap_or(false);
+
+        RequestPage {
+            requests,
+            next_cursor: if more { request_id } else { 0 },
+        }
+    }
+
+    /// Page through requests flagged as overdue
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `cursor` - First request ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of requests (defaults to 50, max 200)
+    pub fn page_overdue_requests(env: Env, cursor: u64, limit: Option<u32>) -> RequestPage {
+        Self::load_request_page(&env, &DataKey::OverdueIndex, cursor, limit, None)
+    }
+
+    /// Page through a hospital's orders
+    ///
+    /// # Arguments
+    /// * `env` - Contract environment
+    /// * `hospital` - Hospital address to query
+    /// * `cursor` - First order ID to include (0 starts from the beginning)
+    /// * `limit` - Maximum number of orders (defaults to 50, max 200)
+    pub fn page_hospital_orders(
+        env: Env,
+        hospital: Address,
+        cursor: u64,
+        limit: Option<u32>,
+    ) -> OrderPage {
+        let key = DataKey::HospitalOrderIndex(hospital);
+        let (page_ids, next_cursor) = Self::page_ids(&env, &key, cursor, limit);
+
+        let mut orders = Vec::new(&env);
+        for id in page_ids.iter() {
+            if let Some(order) = storage::get_blood_order(&env, id) {
+                orders.push_back(order);
+            }
+        }
+
+        OrderPage {
+            orders,
+            next_cursor,
+        }
+    }
+
     // ========== Helper Functions ==========
 
+    /// Fail with `ContractPaused` if an operation family is paused
+    fn require_not_paused(env: &Env, scope: PauseScope) -> Result<(), ContractError> {
+        if storage::is_paused(env, scope) {
+            return Err(ContractError::ContractPaused);
+        }
+        Ok(())
+    }
+
+    /// Count a new request against its hospital's limits
+    ///
+    /// Requests over a limit fail with `RequestLimitExceeded`, except critical
+    /// ones, which return the window and open-request counts so they can be
+    /// flagged for review.
+    fn count_against_rate_limit(
+        env: &Env,
+        hospital: &Address,
+        urgency: UrgencyLevel,
+    ) -> Result<Option<(u32, u32)>, ContractError> {
+        let Some(limit) = storage::get_rate_limit(env, hospital) else {
+            return Ok(None);
+        };
+
+        let now = env.ledger().timestamp();
+        let mut window = match storage::get_request_window(env, hospital) {
+            Some(window) if now < window.start.saturating_add(limit.window_secs) => window,
+            _ => RequestWindow {
+                start: now,
+                count: 0,
+            },
+        };
+
+        let mut open_requests = 0u32;
+        let hospital_index = DataKey::HospitalIndex(hospital.clone());
+        for request_id in storage::get_index(env, &hospital_index).iter() {
+            if let Some(request) = storage::get_blood_request(env, request_id) {
+                if request.status == RequestStatus::Pending
+                    || request.status == RequestStatus::Approved
+                {
+                    open_requests += 1;
+                }
+            }
+        }
+
+        let over_rate = limit.max_requests > 0 && window.count >= limit.max_requests;
+        let over_open = limit.max_open > 0 && open_requests >= limit.max_open;
+        let over_limit = over_rate || over_open;
+
+        if over_limit && urgency != UrgencyLevel::Critical {
+            return Err(ContractError::RequestLimitExceeded);
+        }
+
+        window.count += 1;
+        storage::set_request_window(env, hospital, &window);
+
+        if over_limit {
+            Ok(Some((window.count, open_requests + 1)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Clamp a requested page size (defaults to 50, max 200)
+    fn page_limit(limit: Option<u32>) -> u32 {
+        match limit {
+            Some(0) | None => DEFAULT_QUERY_LIMIT,
+            Some(limit) => limit.min(MAX_QUERY_LIMIT),
+        }
+    }
+
+    /// Read one page of an index starting at the first ID >= cursor
+    ///
+    /// Returns the IDs on the page and the first ID of the next page (0 when done)
+    fn page_ids(env: &Env, key: &DataKey, cursor: u64, limit: Option<u32>) -> (Vec<u64>, u64) {
+        let limit = Self::page_limit(limit);
+        let mut page = Vec::new(env);
+        let next_cursor = storage::index_walk(env, key, cursor, |id| {
+            if page.len() >= limit {
+                return false;
+            }
+            page.push_back(id);
+            true
+        });
+
+        (page, next_cursor)
+    }
+
+    /// Load one page of requests from an index
+    fn load_request_page(
+        env: &Env,
+        key: &DataKey,
+        cursor: u64,
+        limit: Option<u32>,
+        status_filter: Option<RequestStatus>,
+    ) -> RequestPage {
+        let (page_ids, next_cursor) = Self::page_ids(env, key, cursor, limit);
+
+        let mut requests = Vec::new(env);
+        for request in Self::load_requests_from_ids(env, page_ids).iter() {
+            if status_filter.is_none() || status_filter == Some(request.status) {
+                requests.push_back(request);
+            }
+        }
+
+        RequestPage {
+            requests,
+            next_cursor,
+        }
+    }
+
+    /// Move a request past its deadline to Expired, releasing assigned units
+    ///
+    /// Returns the request as stored after the check.
+    fn expire_if_overdue(env: &Env, mut request: BloodRequest) -> BloodRequest {
+        let current_time = env.ledger().timestamp();
+        if !request.is_expired(current_time)
+            || !request.status.can_transition_to(&RequestStatus::Expired)
+        {
+            return request;
+        }
+
+        let old_status = request.status;
+        request.status = RequestStatus::Expired;
+
+        let released_units = request.assigned_units.clone();
+        request.assigned_units = Vec::new(env);
+
+        storage::set_blood_request(env, &request);
+        storage::update_status_index(env, request.id, old_status, RequestStatus::Expired);
+        storage::remove_from_overdue_index(env, request.id);
+
+        events::emit_request_expired(env, request.id, old_status);
+        if !released_units.is_empty() {
+            events::emit_units_released(env, request.id, released_units);
+        }
+
+        request
+    }
+
+    /// Apply deadline escalation and overdue flagging to a single request
+    fn escalate_request(env: &Env, mut request: BloodRequest, current_time: u64) {
+        let is_open =
+            request.status == RequestStatus::Pending || request.status == RequestStatus::Approved;
+        if !is_open {
+            // Closed requests no longer need chasing
+            storage::remove_from_overdue_index(env, request.id);
+            return;
+        }
+
+        let remaining = request.required_by.saturating_sub(current_time);
+        let target = if remaining < ESCALATE_TO_CRITICAL_SECONDS {
+            UrgencyLevel::Critical
+        } else if remaining < ESCALATE_TO_URGENT_SECONDS {
+            UrgencyLevel::Urgent
+        } else {
+            request.urgency
+        };
+
+        if target.is_higher_than(&request.urgency) {
+            let old_urgency = request.urgency;
+            request.urgency = target;
+
+            storage::set_blood_request(env, &request);
+            storage::update_urgency_index(env, request.id, old_urgency, target);
+
+            events::emit_urgency_escalated(
+                env,
+                request.id,
+                old_urgency,
+                target,
+                request.required_by,
+            );
+        }
+
+        if current_time >= request.required_by && !storage::is_overdue(env, request.id) {
+            storage::add_to_overdue_index(env, request.id);
+            events::emit_request_overdue(
+                env,
+                request.id,
+                &request.hospital_id,
+                request.required_by,
+            );
+        }
+    }
+
     /// Load full BloodRequest objects from a vector of request IDs
     fn load_requests_from_ids(env: &Env, ids: Vec<u64>) -> Vec<BloodRequest> {
         let mut requests = Vec::new(env);
         for id in ids.iter() {
             if let Some(request) = storage::get_blood_request(env, id) {
-                requests.push_back(request);
+                requests.push_back(Self::expire_if_overdue(env, request));
             }
         }
         requests
@@ -646,14 +1703,14 @@ impl RequestContract {
         
         // If offset is beyond the length, return empty vector
         if offset_val >= total {
-            return Vec::new(&env);
+            return Vec::new(env);
         }
         
         // Calculate end index
         let end = (offset_val + limit_val).min(total);
         
         // Slice the vector
-        let mut result = Vec::new(&env);
+        let mut result = Vec::new(env);
         for i in offset_val..end {
             if let Some(request) = requests.get(i as u32) {
                 result.push_back(request);
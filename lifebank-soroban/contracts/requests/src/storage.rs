@@ -1,4 +1,7 @@
-use crate::types::{BloodRequest, BloodType, DataKey, RequestStatus, UrgencyLevel};
+use crate::types::{
+    BloodOrder, BloodRequest, BloodType, DataKey, PauseScope, RateLimit, RequestStatus,
+    RequestWindow, UrgencyLevel,
+};
 use soroban_sdk::{Address, Env, Vec};
 
 /// Maximum time window for request fulfillment (30 days)
@@ -16,10 +19,6 @@ pub const MIN_REQUEST_WINDOW_SECONDS: u64 = 3600;
 ///
 /// # Panics
 /// Panics if admin has not been set (contract not initialized)
-use crate::types::{BloodRequest, DataKey};
-use soroban_sdk::{Address, Env, Vec};
-
-/// Get the admin address
 pub fn get_admin(env: &Env) -> Address {
     env.storage()
         .instance()
@@ -37,6 +36,65 @@ pub fn is_initialized(env: &Env) -> bool {
     env.storage().instance().has(&DataKey::Admin)
 }
 
+/// Get the guardian address, if one has been appointed
+pub fn get_guardian(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::Guardian)
+}
+
+/// Set the guardian address
+pub fn set_guardian(env: &Env, guardian: &Address) {
+    env.storage().instance().set(&DataKey::Guardian, guardian);
+}
+
+/// Check if an operation family is paused, on its own or by `PauseScope::All`
+pub fn is_paused(env: &Env, scope: PauseScope) -> bool {
+    let storage = env.storage().instance();
+    storage.has(&DataKey::Paused(PauseScope::All)) || storage.has(&DataKey::Paused(scope))
+}
+
+/// Pause or unpause an operation family
+pub fn set_paused(env: &Env, scope: PauseScope, paused: bool) {
+    let key = DataKey::Paused(scope);
+    if paused {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+}
+
+// ========== Rate Limits ==========
+
+/// Get the request limits for a hospital, if any are set
+pub fn get_rate_limit(env: &Env, hospital: &Address) -> Option<RateLimit> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::RateLimit(hospital.clone()))
+}
+
+/// Set the request limits for a hospital and start a fresh window
+pub fn set_rate_limit(env: &Env, hospital: &Address, limit: &RateLimit) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::RateLimit(hospital.clone()), limit);
+    env.storage()
+        .persistent()
+        .remove(&DataKey::RequestWindow(hospital.clone()));
+}
+
+/// Get a hospital's current rate window, if one has started
+pub fn get_request_window(env: &Env, hospital: &Address) -> Option<RequestWindow> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::RequestWindow(hospital.clone()))
+}
+
+/// Store a hospital's current rate window
+pub fn set_request_window(env: &Env, hospital: &Address, window: &RequestWindow) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::RequestWindow(hospital.clone()), window);
+}
+
 // ========== Hospital Authorization ==========
 
 /// Check if a hospital is authorized to create requests
@@ -70,7 +128,6 @@ pub fn revoke_hospital(env: &Env, hospital: &Address) {
 
 // ========== Request ID Generation ==========
 
-/// Get the current request counter value
 /// Get the current request counter
 pub fn get_request_counter(env: &Env) -> u64 {
     env.storage()
@@ -91,6 +148,13 @@ pub fn increment_request_id(env: &Env) -> u64 {
     next_id
 }
 
+/// Set the request counter (index repair only)
+pub fn set_request_counter(env: &Env, value: u64) {
+    env.storage()
+        .instance()
+        .set(&DataKey::RequestCounter, &value);
+}
+
 // ========== Blood Request Storage ==========
 
 /// Store a blood request
@@ -117,73 +181,216 @@ pub fn remove_blood_request(env: &Env, id: u64) {
     env.storage().persistent().remove(&DataKey::BloodRequest(id));
 }
 
-// ========== Index Management ==========
+// ========== Blood Order Storage ==========
 
-/// Add request to hospital index
-pub fn add_to_hospital_index(env: &Env, request: &BloodRequest) {
-    let key = DataKey::HospitalIndex(request.hospital_id.clone());
-    let mut requests: Vec<u64> = env
+/// Increment and return the next order ID
+///
+/// IDs start at 1 and increment sequentially
+pub fn increment_order_id(env: &Env) -> u64 {
+    let next_id = env
         .storage()
+        .instance()
+        .get(&DataKey::OrderCounter)
+        .unwrap_or(0u64)
+        + 1;
+    env.storage()
+        .instance()
+        .set(&DataKey::OrderCounter, &next_id);
+    next_id
+}
+
+/// Store a blood order
+pub fn set_blood_order(env: &Env, order: &BloodOrder) {
+    env.storage()
         .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env));
+        .set(&DataKey::BloodOrder(order.id), order);
+}
 
-    requests.push_back(request.id);
-    env.storage().persistent().set(&key, &requests);
+/// Get a blood order by ID
+pub fn get_blood_order(env: &Env, id: u64) -> Option<BloodOrder> {
+    env.storage().persistent().get(&DataKey::BloodOrder(id))
 }
 
-/// Add request to blood type index
-pub fn add_to_blood_type_index(env: &Env, request: &BloodRequest) {
-    let key = DataKey::BloodTypeIndex(request.blood_type);
-    let mut requests: Vec<u64> = env
-        .storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env));
+/// Add order to hospital order index
+pub fn add_to_hospital_order_index(env: &Env, order: &BloodOrder) {
+    index_insert(
+        env,
+        &DataKey::HospitalOrderIndex(order.hospital_id.clone()),
+        order.id,
+    );
+}
 
-    requests.push_back(request.id);
-    env.storage().persistent().set(&key, &requests);
+/// Get all order IDs for a hospital
+pub fn get_orders_by_hospital(env: &Env, hospital: &Address) -> Vec<u64> {
+    get_index(env, &DataKey::HospitalOrderIndex(hospital.clone()))
 }
 
-/// Add request to status index
-pub fn add_to_status_index(env: &Env, request: &BloodRequest) {
-    let key = DataKey::StatusIndex(request.status);
-    let mut requests: Vec<u64> = env
-        .storage()
+// ========== Index Management ==========
+//
+// Each index lists request IDs in ascending order, split into buckets of
+// `INDEX_BUCKET_SIZE` consecutive IDs stored under `(key, bucket)`. The index
+// key itself holds the sorted list of non-empty buckets, so an update only
+// rewrites one bucket and a page only reads the buckets it spans, however
+// large the index grows.
+
+/// Consecutive IDs stored together in one index bucket
+pub const INDEX_BUCKET_SIZE: u64 = 64;
+
+/// Get the sorted list of non-empty buckets of an index
+fn get_buckets(env: &Env, key: &DataKey) -> Vec<u64> {
+    env.storage().persistent().get(key).unwrap_or(Vec::new(env))
+}
+
+/// Get the IDs stored in one bucket of an index
+fn get_bucket(env: &Env, key: &DataKey, bucket: u64) -> Vec<u64> {
+    env.storage()
         .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env));
+        .get(&(key.clone(), bucket))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Insert an ID into an index, ignoring duplicates
+pub fn index_insert(env: &Env, key: &DataKey, id: u64) {
+    let bucket = id / INDEX_BUCKET_SIZE;
+    let mut ids = get_bucket(env, key, bucket);
 
-    requests.push_back(request.id);
-    env.storage().persistent().set(&key, &requests);
+    if let Err(pos) = ids.binary_search(id) {
+        if ids.is_empty() {
+            let mut buckets = get_buckets(env, key);
+            insert_sorted(&mut buckets, bucket);
+            env.storage().persistent().set(key, &buckets);
+        }
+        ids.insert(pos, id);
+        env.storage().persistent().set(&(key.clone(), bucket), &ids);
+    }
 }
 
-/// Add request to urgency index
-pub fn add_to_urgency_index(env: &Env, request: &BloodRequest) {
-    let key = DataKey::UrgencyIndex(request.urgency);
-    let mut requests: Vec<u64> = env
-        .storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env));
-
-    requests.push_back(request.id);
-    env.storage().persistent().set(&key, &requests);
-}
-
-/// Remove request from status index (for status transitions)
-pub fn remove_from_status_index(env: &Env, request_id: u64, status: RequestStatus) {
-    let key = DataKey::StatusIndex(status);
-    if let Some(requests) = env.storage().persistent().get::<DataKey, Vec<u64>>(&key) {
-        // Find and remove the request ID
-        let mut new_requests = Vec::new(env);
-        for id in requests.iter() {
-            if id != request_id {
-                new_requests.push_back(id);
+/// Remove an ID from an index
+pub fn index_remove(env: &Env, key: &DataKey, id: u64) {
+    let bucket = id / INDEX_BUCKET_SIZE;
+    let mut ids = get_bucket(env, key, bucket);
+
+    if let Some(pos) = ids.first_index_of(id) {
+        ids.remove(pos);
+        if !ids.is_empty() {
+            env.storage().persistent().set(&(key.clone(), bucket), &ids);
+            return;
+        }
+
+        // Drop the emptied bucket so pages skip it
+        env.storage().persistent().remove(&(key.clone(), bucket));
+        let mut buckets = get_buckets(env, key);
+        if let Ok(at) = buckets.binary_search(bucket) {
+            buckets.remove(at);
+            env.storage().persistent().set(key, &buckets);
+        }
+    }
+}
+
+/// Check whether an index holds an ID
+pub fn index_contains(env: &Env, key: &DataKey, id: u64) -> bool {
+    get_bucket(env, key, id / INDEX_BUCKET_SIZE).contains(id)
+}
+
+/// Visit the IDs of an index in order, from the first ID >= cursor
+///
+/// Stops at the first ID `visit` declines and returns it, or 0 once the
+/// index is exhausted. Only the buckets actually walked are read.
+pub fn index_walk(
+    env: &Env,
+    key: &DataKey,
+    cursor: u64,
+    mut visit: impl FnMut(u64) -> bool,
+) -> u64 {
+    let buckets = get_buckets(env, key);
+    let start = match buckets.binary_search(cursor / INDEX_BUCKET_SIZE) {
+        Ok(pos) | Err(pos) => pos,
+    };
+
+    for bucket in buckets.slice(start..buckets.len()).iter() {
+        for id in get_bucket(env, key, bucket).iter() {
+            if id >= cursor && !visit(id) {
+                return id;
             }
         }
-        env.storage().persistent().set(&key, &new_requests);
     }
+
+    0
+}
+
+/// Get all IDs stored under an index key, bucket by bucket
+pub fn get_index(env: &Env, key: &DataKey) -> Vec<u64> {
+    let mut ids = Vec::new(env);
+    for bucket in get_buckets(env, key).iter() {
+        ids.append(&get_bucket(env, key, bucket));
+    }
+    ids
+}
+
+/// Replace all IDs stored under an index key (index repair only)
+///
+/// IDs keep their given order within each bucket.
+pub fn set_index(env: &Env, key: &DataKey, ids: &Vec<u64>) {
+    for bucket in get_buckets(env, key).iter() {
+        env.storage().persistent().remove(&(key.clone(), bucket));
+    }
+
+    let mut buckets = Vec::new(env);
+    for id in ids.iter() {
+        insert_sorted(&mut buckets, id / INDEX_BUCKET_SIZE);
+    }
+    for bucket in buckets.iter() {
+        let mut bucket_ids = Vec::new(env);
+        for id in ids.iter() {
+            if id / INDEX_BUCKET_SIZE == bucket {
+                bucket_ids.push_back(id);
+            }
+        }
+        env.storage()
+            .persistent()
+            .set(&(key.clone(), bucket), &bucket_ids);
+    }
+
+    if buckets.is_empty() {
+        env.storage().persistent().remove(key);
+    } else {
+        env.storage().persistent().set(key, &buckets);
+    }
+}
+
+/// Insert an ID into a sorted list, ignoring duplicates
+pub fn insert_sorted(ids: &mut Vec<u64>, id: u64) {
+    if let Err(pos) = ids.binary_search(id) {
+        ids.insert(pos, id);
+    }
+}
+
+/// Add request to hospital index
+pub fn add_to_hospital_index(env: &Env, request: &BloodRequest) {
+    index_insert(
+        env,
+        &DataKey::HospitalIndex(request.hospital_id.clone()),
+        request.id,
+    );
+}
+
+/// Add request to blood type index
+pub fn add_to_blood_type_index(env: &Env, request: &BloodRequest) {
+    index_insert(
+        env,
+        &DataKey::BloodTypeIndex(request.blood_type),
+        request.id,
+    );
+}
+
+/// Add request to status index
+pub fn add_to_status_index(env: &Env, request: &BloodRequest) {
+    index_insert(env, &DataKey::StatusIndex(request.status), request.id);
+}
+
+/// Add request to urgency index
+pub fn add_to_urgency_index(env: &Env, request: &BloodRequest) {
+    index_insert(env, &DataKey::UrgencyIndex(request.urgency), request.id);
 }
 
 /// Update status index when request status changes
@@ -193,70 +400,59 @@ pub fn update_status_index(
     old_status: RequestStatus,
     new_status: RequestStatus,
 ) {
-    remove_from_status_index(env, request_id, old_status);
+    index_remove(env, &DataKey::StatusIndex(old_status), request_id);
+    index_insert(env, &DataKey::StatusIndex(new_status), request_id);
+}
 
-    // Add to new status index
-    let key = DataKey::StatusIndex(new_status);
-    let mut requests: Vec<u64> = env
-        .storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env));
-    requests.push_back(request_id);
-    env.storage().persistent().set(&key, &requests);
+/// Update urgency index when a request is escalated
+pub fn update_urgency_index(
+    env: &Env,
+    request_id: u64,
+    old_urgency: UrgencyLevel,
+    new_urgency: UrgencyLevel,
+) {
+    index_remove(env, &DataKey::UrgencyIndex(old_urgency), request_id);
+    index_insert(env, &DataKey::UrgencyIndex(new_urgency), request_id);
+}
+
+/// Flag a request as overdue
+pub fn add_to_overdue_index(env: &Env, request_id: u64) {
+    index_insert(env, &DataKey::OverdueIndex, request_id);
+}
+
+/// Clear the overdue flag for a request
+pub fn remove_from_overdue_index(env: &Env, request_id: u64) {
+    index_remove(env, &DataKey::OverdueIndex, request_id);
+}
+
+/// Check if a request is flagged as overdue
+pub fn is_overdue(env: &Env, request_id: u64) -> bool {
+    index_contains(env, &DataKey::OverdueIndex, request_id)
+}
+
+/// Get all request IDs flagged as overdue
+pub fn get_overdue_requests(env: &Env) -> Vec<u64> {
+    get_index(env, &DataKey::OverdueIndex)
 }
 
 // ========== Index Queries ==========
 
 /// Get all request IDs for a hospital
 pub fn get_requests_by_hospital(env: &Env, hospital: &Address) -> Vec<u64> {
-    let key = DataKey::HospitalIndex(hospital.clone());
-    env.storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env))
+    get_index(env, &DataKey::HospitalIndex(hospital.clone()))
 }
 
 /// Get all request IDs for a blood type
 pub fn get_requests_by_blood_type(env: &Env, blood_type: BloodType) -> Vec<u64> {
-    let key = DataKey::BloodTypeIndex(blood_type);
-    env.storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env))
+    get_index(env, &DataKey::BloodTypeIndex(blood_type))
 }
 
 /// Get all request IDs with a specific status
 pub fn get_requests_by_status(env: &Env, status: RequestStatus) -> Vec<u64> {
-    let key = DataKey::StatusIndex(status);
-    env.storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env))
+    get_index(env, &DataKey::StatusIndex(status))
 }
 
 /// Get all request IDs with a specific urgency level
 pub fn get_requests_by_urgency(env: &Env, urgency: UrgencyLevel) -> Vec<u64> {
-    let key = DataKey::UrgencyIndex(urgency);
-    env.storage()
-        .persistent()
-        .get(&key)
-        .unwrap_or(Vec::new(env))
-/// Retrieve a blood request by ID
-pub fn get_blood_request(env: &Env, request_id: u64) -> Option<BloodRequest> {
-    env.storage()
-        .persistent()
-        .get(&DataKey::BloodRequest(request_id))
-}
-
-/// Check if a hospital is authorized
-pub fn is_authorized_hospital(env: &Env, hospital: &Address) -> bool {
-    let admin = get_admin(env);
-    hospital == &admin
-}
-
-/// Check if a blood bank is authorized
-pub fn is_authorized_blood_bank(env: &Env, bank: &Address) -> bool {
-    let admin = get_admin(env);
-    bank == &admin
+    get_index(env, &DataKey::UrgencyIndex(urgency))
 }
@@ -1,16 +1,14 @@
-use crate::types::{BloodRequest, BloodType, RequestMetadata, RequestStatus, UrgencyLevel};
-use crate::{RequestContract, RequestContractClient};
-use soroban_sdk::{
-    testutils::{Address as _, Ledger},
-    vec, Address, Env, String,
-};
-
+use crate::error::ContractError;
 use crate::storage;
-use crate::types::{BloodType, RequestStatus, UrgencyLevel};
+use crate::types::{
+    BloodRequest, BloodType, DataKey, EntityId, EventEnvelope, EventPayload, IndexIssue, IndexKind, IssueKind,
+    OrderLineRequest, OrderStatus, PauseScope, ProductType, RateLimit, RequestStatus, UrgencyLevel,
+    EVENT_SCHEMA_VERSION,
+};
 use crate::{RequestContract, RequestContractClient};
 use soroban_sdk::{
-    testutils::{Address as _, Ledger},
-    Address, Env, String,
+    testutils::{Address as _, Events, Ledger},
+    vec, Address, Env, String, Symbol, TryFromVal, Vec,
 };
 
 // ========== Test Helpers ==========
@@ -118,71 +116,27 @@ fn test_create_request_success() {
 
     let request_id = client.create_request(
         &hospital,
-fn create_test_contract<'a>() -> (Env, Address, RequestContractClient<'a>, Address) {
-    let env = Env::default();
-    env.mock_all_auths();
-
-    let contract_id = env.register(RequestContract, ());
-    let client = RequestContractClient::new(&env, &contract_id);
-
-    let admin = Address::generate(&env);
-    client.initialize(&admin);
-
-    (env, admin, client, contract_id)
-}
-
-#[test]
-fn test_initialize_success() {
-    let (env, admin, _client, contract_id) = create_test_contract();
-
-    // Verify admin is set
-    let stored_admin = env.as_contract(&contract_id, || {
-        crate::storage::get_admin(&env)
-    });
-
-    assert_eq!(stored_admin, admin);
-}
-
-#[test]
-#[should_panic(expected = "Error(Contract, #0)")]
-fn test_initialize_already_initialized() {
-    let (env, admin, client, _contract_id) = create_test_contract();
-
-    // Try to initialize again
-    client.initialize(&admin);
-}
-
-#[test]
-#[should_panic(expected = "Error(Contract, #32)")]
-fn test_create_request_success() {
-    let (env, admin, client, _contract_id) = create_test_contract();
-
-    let hospital = Address::generate(&env);
-    let patient = Address::generate(&env);
-    let blood_type = BloodType::OPositive;
-    let quantity_ml = 450u32;
-    let urgency = UrgencyLevel::Urgent;
-
-    let current_time = 1000u64;
-    env.ledger().set_timestamp(current_time);
-    let required_by = current_time + (2 * 86400); // 2 days from now
-
-    let delivery_address = String::from_str(&env, "Hospital Main Building");
-    let procedure = String::from_str(&env, "Emergency Surgery");
-    let notes = String::from_str(&env, "Type O+ preferred");
-
-    // This should fail because hospital is not authorized (not the admin)
-    client.create_request(
-        &hospital,
         &blood_type,
         &quantity_ml,
         &urgency,
         &required_by,
         &delivery_address,
-        &patient,
-        &procedure,
-        &notes,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
+
+    assert_eq!(request_id, 1);
+
+    let request = client.get_request(&request_id);
+    assert_eq!(request.hospital_id, hospital);
+    assert_eq!(request.blood_type, blood_type);
+    assert_eq!(request.quantity_ml, quantity_ml);
+    assert_eq!(request.urgency, urgency);
+    assert_eq!(request.status, RequestStatus::Pending);
+    assert_eq!(request.created_at, current_time);
+    assert_eq!(request.required_by, required_by);
+    assert_eq!(request.delivery_address, delivery_address);
 }
 
 #[test]
@@ -225,12 +179,12 @@ fn test_create_request_invalid_quantity_too_low() {
 
     let patient = Address::generate(&env);
     let blood_type = BloodType::BPositive;
-    let quantity_ml = 25u32; // Below minimum of 50ml
+    let quantity_ml = 25u32; // Below minimum of 100ml
     let urgency = UrgencyLevel::Critical;
 
     let current_time = 1000u64;
     env.ledger().set_timestamp(current_time);
-    let required_by = current_time + (1 * 86400);
+    let required_by = current_time + 86400;
 
     let delivery_address = String::from_str(&env, "Hospital");
     let procedure = String::from_str(&env, "Surgery");
@@ -256,7 +210,7 @@ fn test_create_request_invalid_quantity_too_high() {
 
     let patient = Address::generate(&env);
     let blood_type = BloodType::BNegative;
-    let quantity_ml = 6000u32; // Above maximum of 5000ml
+    let quantity_ml = 10001u32; // Above maximum of 10000ml
     let urgency = UrgencyLevel::Normal;
 
     let current_time = 1000u64;
@@ -281,7 +235,7 @@ fn test_create_request_invalid_quantity_too_high() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #15)")]
+#[should_panic(expected = "Error(Contract, #17)")]
 fn test_create_request_invalid_timestamp_in_past() {
     let (env, admin, client, _contract_id) = create_test_contract();
 
@@ -312,7 +266,7 @@ fn test_create_request_invalid_timestamp_in_past() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #15)")]
+#[should_panic(expected = "Error(Contract, #17)")]
 fn test_create_request_invalid_timestamp_too_far() {
     let (env, admin, client, _contract_id) = create_test_contract();
 
@@ -343,7 +297,7 @@ fn test_create_request_invalid_timestamp_too_far() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #12)")]
+#[should_panic(expected = "Error(Contract, #19)")]
 fn test_create_request_empty_delivery_address() {
     let (env, admin, client, _contract_id) = create_test_contract();
 
@@ -354,7 +308,7 @@ fn test_create_request_empty_delivery_address() {
 
     let current_time = 1000u64;
     env.ledger().set_timestamp(current_time);
-    let required_by = current_time + (1 * 86400);
+    let required_by = current_time + 86400;
 
     let delivery_address = String::from_str(&env, ""); // Empty address
     let procedure = String::from_str(&env, "Surgery");
@@ -543,10 +497,10 @@ fn test_update_request_status_from_terminal_state() {
         &notes,
     );
 
-    // Transition to Rejected (terminal state)
-    client.update_request_status(&request_id, &RequestStatus::Rejected);
+    // Transition to Cancelled (terminal state)
+    client.update_request_status(&request_id, &RequestStatus::Cancelled);
 
-    // Try to transition from Rejected (should fail)
+    // Try to transition from Cancelled (should fail)
     client.update_request_status(&request_id, &RequestStatus::Approved);
 }
 
@@ -585,9 +539,9 @@ fn test_assign_blood_units() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #40)")]
+#[should_panic(expected = "Error(Contract, #21)")]
 fn test_request_not_found() {
-    let (env, _admin, client, _contract_id) = create_test_contract();
+    let (_env, _admin, client, _contract_id) = create_test_contract();
 
     // Try to get a request that doesn't exist
     client.get_request(&999u64);
@@ -604,17 +558,22 @@ fn test_urgency_level_max_fulfillment_time() {
 fn test_request_status_transitions() {
     // Test valid transitions
     assert!(RequestStatus::Pending.can_transition_to(&RequestStatus::Approved));
-    assert!(RequestStatus::Pending.can_transition_to(&RequestStatus::Rejected));
     assert!(RequestStatus::Pending.can_transition_to(&RequestStatus::Cancelled));
+    assert!(RequestStatus::Pending.can_transition_to(&RequestStatus::Expired));
 
     assert!(RequestStatus::Approved.can_transition_to(&RequestStatus::Fulfilled));
     assert!(RequestStatus::Approved.can_transition_to(&RequestStatus::Cancelled));
+    assert!(RequestStatus::Approved.can_transition_to(&RequestStatus::Expired));
 
-    assert!(RequestStatus::Fulfilled.can_transition_to(&RequestStatus::Completed));
+    assert!(RequestStatus::Fulfilled.can_transition_to(&RequestStatus::InDelivery));
+    assert!(RequestStatus::InDelivery.can_transition_to(&RequestStatus::Completed));
 
     // Test invalid transitions
     assert!(!RequestStatus::Pending.can_transition_to(&RequestStatus::Fulfilled));
-    assert!(!RequestStatus::Rejected.can_transition_to(&RequestStatus::Approved));
+    assert!(!RequestStatus::Fulfilled.can_transition_to(&RequestStatus::Expired));
+    assert!(!RequestStatus::InDelivery.can_transition_to(&RequestStatus::Expired));
+    assert!(!RequestStatus::Expired.can_transition_to(&RequestStatus::Pending));
+    assert!(!RequestStatus::Expired.can_transition_to(&RequestStatus::Cancelled));
     assert!(!RequestStatus::Completed.can_transition_to(&RequestStatus::Approved));
     assert!(!RequestStatus::Cancelled.can_transition_to(&RequestStatus::Fulfilled));
 }
@@ -624,10 +583,11 @@ fn test_request_status_is_terminal() {
     assert!(!RequestStatus::Pending.is_terminal());
     assert!(!RequestStatus::Approved.is_terminal());
     assert!(!RequestStatus::Fulfilled.is_terminal());
+    assert!(!RequestStatus::InDelivery.is_terminal());
 
     assert!(RequestStatus::Completed.is_terminal());
-    assert!(RequestStatus::Rejected.is_terminal());
     assert!(RequestStatus::Cancelled.is_terminal());
+    assert!(RequestStatus::Expired.is_terminal());
 }
 
 #[test]
@@ -637,7 +597,6 @@ fn test_blood_request_validate_all_blood_types() {
     env.ledger().set_timestamp(1000u64);
 
     let hospital = Address::generate(&env);
-    let patient = Address::generate(&env);
 
     let blood_types = [
         BloodType::APositive,
@@ -651,11 +610,6 @@ fn test_blood_request_validate_all_blood_types() {
     ];
 
     for blood_type in blood_types.iter() {
-        let metadata = RequestMetadata {
-            patient_id: patient.clone(),
-            procedure: String::from_str(&env, "Surgery"),
-            notes: String::from_str(&env, "Notes"),
-        };
 
         let request = BloodRequest {
             id: 1,
@@ -669,7 +623,7 @@ fn test_blood_request_validate_all_blood_types() {
             fulfilled_at: None,
             assigned_units: soroban_sdk::vec![&env],
             delivery_address: String::from_str(&env, "Hospital"),
-            metadata,
+            metadata: soroban_sdk::Map::new(&env),
         };
 
         assert!(request.validate(1000u64).is_ok());
@@ -682,13 +636,6 @@ fn test_blood_request_is_overdue() {
     env.mock_all_auths();
 
     let hospital = Address::generate(&env);
-    let patient = Address::generate(&env);
-
-    let metadata = RequestMetadata {
-        patient_id: patient,
-        procedure: String::from_str(&env, "Surgery"),
-        notes: String::from_str(&env, "Notes"),
-    };
 
     let request = BloodRequest {
         id: 1,
@@ -702,7 +649,7 @@ fn test_blood_request_is_overdue() {
         fulfilled_at: None,
         assigned_units: soroban_sdk::vec![&env],
         delivery_address: String::from_str(&env, "Hospital"),
-        metadata,
+        metadata: soroban_sdk::Map::new(&env),
     };
 
     assert!(!request.is_overdue(1500u64)); // Before deadline
@@ -716,13 +663,6 @@ fn test_blood_request_time_remaining() {
     env.mock_all_auths();
 
     let hospital = Address::generate(&env);
-    let patient = Address::generate(&env);
-
-    let metadata = RequestMetadata {
-        patient_id: patient,
-        procedure: String::from_str(&env, "Surgery"),
-        notes: String::from_str(&env, "Notes"),
-    };
 
     let request = BloodRequest {
         id: 1,
@@ -736,7 +676,7 @@ fn test_blood_request_time_remaining() {
         fulfilled_at: None,
         assigned_units: soroban_sdk::vec![&env],
         delivery_address: String::from_str(&env, "Hospital"),
-        metadata,
+        metadata: soroban_sdk::Map::new(&env),
     };
 
     assert_eq!(request.time_remaining(1000u64), 1000i64); // 1000 seconds remaining
@@ -751,13 +691,6 @@ fn test_blood_request_can_fulfill() {
     env.mock_all_auths();
 
     let hospital = Address::generate(&env);
-    let patient = Address::generate(&env);
-
-    let metadata = RequestMetadata {
-        patient_id: patient,
-        procedure: String::from_str(&env, "Surgery"),
-        notes: String::from_str(&env, "Notes"),
-    };
 
     let mut request = BloodRequest {
         id: 1,
@@ -771,7 +704,7 @@ fn test_blood_request_can_fulfill() {
         fulfilled_at: None,
         assigned_units: soroban_sdk::vec![&env],
         delivery_address: String::from_str(&env, "Hospital"),
-        metadata,
+        metadata: soroban_sdk::Map::new(&env),
     };
 
     // Can fulfill when Approved and not overdue
@@ -785,7 +718,6 @@ fn test_blood_request_can_fulfill() {
     assert!(!request.can_fulfill(1500u64));
 }
 
-
 #[test]
 fn test_create_request_as_admin_success() {
     let (env, admin, client, _contract_id) = create_test_contract();
@@ -810,6 +742,9 @@ fn test_create_request_as_admin_success() {
         &urgency,
         &required_by,
         &delivery_address,
+        &patient,
+        &procedure,
+        &notes,
     );
 
     assert_eq!(request_id, 1);
@@ -817,7 +752,7 @@ fn test_create_request_as_admin_success() {
     // Verify stored request
     let stored_request = client.get_request(&request_id);
     assert_eq!(stored_request.id, 1);
-    assert_eq!(stored_request.hospital_id, hospital);
+    assert_eq!(stored_request.hospital_id, admin);
     assert_eq!(stored_request.blood_type, blood_type);
     assert_eq!(stored_request.quantity_ml, quantity_ml);
     assert_eq!(stored_request.urgency, urgency);
@@ -848,6 +783,9 @@ fn test_create_request_increments_id() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     assert_eq!(id1, 1);
 
@@ -859,6 +797,9 @@ fn test_create_request_increments_id() {
         &UrgencyLevel::Urgent,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     assert_eq!(id2, 2);
 
@@ -870,6 +811,9 @@ fn test_create_request_increments_id() {
         &UrgencyLevel::Critical,
         &(current_time + 2 * 3600), // Critical needs less time
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     assert_eq!(id3, 3);
 }
@@ -905,6 +849,9 @@ fn test_create_request_all_blood_types() {
             &UrgencyLevel::Normal,
             &required_by,
             &delivery_address,
+            &Address::generate(&env),
+            &String::from_str(&env, "Surgery"),
+            &String::from_str(&env, "Notes"),
         );
 
         assert_eq!(id, (i + 1) as u64);
@@ -933,6 +880,9 @@ fn test_create_request_all_urgency_levels() {
         &UrgencyLevel::Critical,
         &(current_time + 2 * 3600), // 2 hours
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let req1 = client.get_request(&id1);
     assert_eq!(req1.urgency, UrgencyLevel::Critical);
@@ -945,6 +895,9 @@ fn test_create_request_all_urgency_levels() {
         &UrgencyLevel::Urgent,
         &(current_time + 6 * 3600), // 6 hours
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let req2 = client.get_request(&id2);
     assert_eq!(req2.urgency, UrgencyLevel::Urgent);
@@ -957,31 +910,14 @@ fn test_create_request_all_urgency_levels() {
         &UrgencyLevel::Normal,
         &(current_time + 48 * 3600), // 48 hours
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let req3 = client.get_request(&id3);
     assert_eq!(req3.urgency, UrgencyLevel::Normal);
 }
 
-#[test]
-#[should_panic(expected = "Error(Contract, #32)")]
-fn test_create_request_unauthorized_hospital() {
-    let (env, _admin, client, _contract_id) = create_test_contract();
-
-    let unauthorized_hospital = Address::generate(&env);
-
-    let current_time = 1000000u64;
-    env.ledger().set_timestamp(current_time);
-
-    client.create_request(
-        &unauthorized_hospital,
-        &BloodType::APositive,
-        &450u32,
-        &UrgencyLevel::Normal,
-        &(current_time + 7 * 86400),
-        &String::from_str(&env, "123 Hospital Street"),
-    );
-}
-
 #[test]
 #[should_panic(expected = "Error(Contract, #16)")]
 fn test_create_request_quantity_too_low() {
@@ -999,6 +935,9 @@ fn test_create_request_quantity_too_low() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1019,6 +958,9 @@ fn test_create_request_quantity_too_high() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1040,6 +982,9 @@ fn test_create_request_required_by_too_soon() {
         &UrgencyLevel::Normal,
         &(current_time + 1800),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1061,26 +1006,9 @@ fn test_create_request_required_by_too_far() {
         &UrgencyLevel::Normal,
         &(current_time + 60 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
-    );
-}
-
-#[test]
-#[should_panic(expected = "Error(Contract, #19)")]
-fn test_create_request_empty_delivery_address() {
-    let (env, _admin, client, _contract_id) = create_test_contract();
-
-    let hospital = setup_authorized_hospital(&env, &client);
-
-    let current_time = 1000000u64;
-    env.ledger().set_timestamp(current_time);
-
-    client.create_request(
-        &hospital,
-        &BloodType::APositive,
-        &450u32,
-        &UrgencyLevel::Normal,
-        &(current_time + 7 * 86400),
-        &String::from_str(&env, ""), // Empty address
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1104,6 +1032,9 @@ fn test_create_request_edge_case_quantities() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let req1 = client.get_request(&id1);
     assert_eq!(req1.quantity_ml, 100);
@@ -1116,6 +1047,9 @@ fn test_create_request_edge_case_quantities() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let req2 = client.get_request(&id2);
     assert_eq!(req2.quantity_ml, 10000);
@@ -1139,6 +1073,9 @@ fn test_approve_request_success() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Verify initial status
@@ -1178,6 +1115,9 @@ fn test_approve_request_already_approved() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Approve first time
@@ -1206,6 +1146,9 @@ fn test_approve_request_expired() {
         &UrgencyLevel::Normal,
         &required_by,
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Fast forward past required_by
@@ -1233,6 +1176,9 @@ fn test_cancel_request_by_hospital() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Cancel by hospital
@@ -1259,6 +1205,9 @@ fn test_cancel_request_by_admin() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Cancel by admin
@@ -1287,6 +1236,9 @@ fn test_cancel_request_unauthorized() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Try to cancel by unauthorized party
@@ -1310,6 +1262,9 @@ fn test_cancel_request_already_cancelled() {
         &UrgencyLevel::Normal,
         &(current_time + 7 * 86400),
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Cancel first time
@@ -1342,6 +1297,9 @@ fn test_get_hospital_requests() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let id2 = client.create_request(
         &hospital1,
@@ -1350,6 +1308,9 @@ fn test_get_hospital_requests() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Create request for hospital2
@@ -1360,6 +1321,9 @@ fn test_get_hospital_requests() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Query hospital1 requests
@@ -1393,6 +1357,9 @@ fn test_get_requests_by_status() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let id2 = client.create_request(
         &hospital,
@@ -1401,6 +1368,9 @@ fn test_get_requests_by_status() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Approve one request
@@ -1437,6 +1407,9 @@ fn test_get_requests_by_blood_type() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let _id2 = client.create_request(
         &hospital,
@@ -1445,6 +1418,9 @@ fn test_get_requests_by_blood_type() {
         &UrgencyLevel::Normal,
         &required_by,
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let id3 = client.create_request(
         &hospital,
@@ -1453,6 +1429,9 @@ fn test_get_requests_by_blood_type() {
         &UrgencyLevel::Urgent,
         &(current_time + 6 * 3600),
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Query A+ requests
@@ -1481,6 +1460,9 @@ fn test_get_requests_by_urgency() {
         &UrgencyLevel::Critical,
         &(current_time + 2 * 3600),
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let _id2 = client.create_request(
         &hospital,
@@ -1489,6 +1471,9 @@ fn test_get_requests_by_urgency() {
         &UrgencyLevel::Normal,
         &(current_time + 48 * 3600),
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
     let id3 = client.create_request(
         &hospital,
@@ -1497,6 +1482,9 @@ fn test_get_requests_by_urgency() {
         &UrgencyLevel::Critical,
         &(current_time + 3 * 3600),
         &delivery_address,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 
     // Query critical requests
@@ -1534,6 +1522,9 @@ fn test_critical_request_insufficient_time() {
         &UrgencyLevel::Critical,
         &(current_time + 1800), // 30 minutes - too short for critical
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1555,6 +1546,9 @@ fn test_urgent_request_insufficient_time() {
         &UrgencyLevel::Urgent,
         &(current_time + 2 * 3600), // 2 hours - too short for urgent
         &String::from_str(&env, "123 Hospital Street"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
 }
 
@@ -1576,25 +1570,10 @@ fn test_normal_request_insufficient_time() {
         &UrgencyLevel::Normal,
         &(current_time + 12 * 3600), // 12 hours - too short for normal
         &String::from_str(&env, "123 Hospital Street"),
-        &patient,
-        &procedure,
-        &notes,
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
     );
-
-    assert_eq!(request_id, 1);
-
-    // Verify request was created
-    let request = client.get_request(&request_id);
-    assert_eq!(request.id, request_id);
-    assert_eq!(request.hospital_id, admin);
-    assert_eq!(request.blood_type, blood_type);
-    assert_eq!(request.quantity_ml, quantity_ml);
-    assert_eq!(request.urgency, urgency);
-    assert_eq!(request.status, RequestStatus::Pending);
-    assert_eq!(request.created_at, current_time);
-    assert_eq!(request.required_by, required_by);
-    assert_eq!(request.fulfilled_at, None);
-    assert_eq!(request.delivery_address, delivery_address);
 }
 
 // ========== Advanced Query Function Tests ==========
@@ -1643,7 +1622,7 @@ fn test_get_request_by_id_non_existent() {
 
 #[test]
 fn test_query_hospital_requests_all() {
-    let (env, admin, client, _contract_id) = create_test_contract();
+    let (env, _admin, client, _contract_id) = create_test_contract();
 
     let hospital = setup_authorized_hospital(&env, &client);
     let patient = Address::generate(&env);
@@ -1699,7 +1678,7 @@ fn test_query_hospital_requests_all() {
 
 #[test]
 fn test_query_hospital_requests_with_status_filter() {
-    let (env, admin, client, _contract_id) = create_test_contract();
+    let (env, _admin, client, _contract_id) = create_test_contract();
 
     let hospital = setup_authorized_hospital(&env, &client);
     let patient = Address::generate(&env);
@@ -1762,7 +1741,7 @@ fn test_query_hospital_requests_with_status_filter() {
 
 #[test]
 fn test_query_hospital_requests_pagination() {
-    let (env, admin, client, _contract_id) = create_test_contract();
+    let (env, _admin, client, _contract_id) = create_test_contract();
 
     let hospital = setup_authorized_hospital(&env, &client);
     let patient = Address::generate(&env);
@@ -1929,7 +1908,7 @@ fn test_query_requests_by_date_range() {
         &BloodType::OPositive,
         &450u32,
         &UrgencyLevel::Normal,
-        &2000u64,
+        &(1000 + 2 * 86400),
         &delivery_address,
         &patient,
         &procedure,
@@ -1942,7 +1921,7 @@ fn test_query_requests_by_date_range() {
         &BloodType::BPositive,
         &500u32,
         &UrgencyLevel::Urgent,
-        &6000u64,
+        &(5000 + 5 * 3600),
         &delivery_address,
         &patient,
         &procedure,
@@ -1955,7 +1934,7 @@ fn test_query_requests_by_date_range() {
         &BloodType::ABNegative,
         &350u32,
         &UrgencyLevel::Critical,
-        &11000u64,
+        &(10000 + 2 * 3600),
         &delivery_address,
         &patient,
         &procedure,
@@ -2009,7 +1988,7 @@ fn test_query_requests_by_date_range_with_status_filter() {
         &BloodType::OPositive,
         &450u32,
         &UrgencyLevel::Normal,
-        &2000u64,
+        &(1000 + 2 * 86400),
         &delivery_address,
         &patient,
         &procedure,
@@ -2022,7 +2001,7 @@ fn test_query_requests_by_date_range_with_status_filter() {
         &BloodType::BPositive,
         &500u32,
         &UrgencyLevel::Urgent,
-        &3000u64,
+        &(2000 + 5 * 3600),
         &delivery_address,
         &patient,
         &procedure,
@@ -2056,7 +2035,7 @@ fn test_query_requests_by_date_range_with_status_filter() {
 }
 
 #[test]
-fn test_query_requests_by_urgency_and_status() {
+fn test_query_by_urgency_and_status() {
     let (env, admin, client, _contract_id) = create_test_contract();
 
     let patient = Address::generate(&env);
@@ -2108,7 +2087,7 @@ fn test_query_requests_by_urgency_and_status() {
     client.approve_request(&req1);
 
     // Query all critical requests (no status filter)
-    let critical_all = client.query_requests_by_urgency_and_status(
+    let critical_all = client.query_by_urgency_and_status(
         &UrgencyLevel::Critical,
         &None,
         &None,
@@ -2117,7 +2096,7 @@ fn test_query_requests_by_urgency_and_status() {
     assert_eq!(critical_all.len(), 2);
 
     // Query critical pending requests only
-    let critical_pending = client.query_requests_by_urgency_and_status(
+    let critical_pending = client.query_by_urgency_and_status(
         &UrgencyLevel::Critical,
         &Some(RequestStatus::Pending),
         &None,
@@ -2126,7 +2105,7 @@ fn test_query_requests_by_urgency_and_status() {
     assert_eq!(critical_pending.len(), 1);
 
     // Query critical approved requests only
-    let critical_approved = client.query_requests_by_urgency_and_status(
+    let critical_approved = client.query_by_urgency_and_status(
         &UrgencyLevel::Critical,
         &Some(RequestStatus::Approved),
         &None,
@@ -2208,3 +2187,858 @@ fn test_max_query_limit_enforcement() {
     assert_eq!(result.len(), 10);
 }
 
+// ========== Deadline Escalation Tests ==========
+
+fn create_normal_request(
+    env: &Env,
+    client: &RequestContractClient<'_>,
+    hospital: &Address,
+    required_by: u64,
+) -> u64 {
+    client.create_request(
+        hospital,
+        &BloodType::OPositive,
+        &450u32,
+        &UrgencyLevel::Normal,
+        &required_by,
+        &String::from_str(env, "Hospital"),
+        &Address::generate(env),
+        &String::from_str(env, "Surgery"),
+        &String::from_str(env, "Notes"),
+    )
+}
+
+#[test]
+fn test_escalate_requests_raises_urgency_and_updates_index() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let required_by = 1000 + 86400;
+    let request_id = create_normal_request(&env, &client, &admin, required_by);
+
+    // More than 12 hours left: nothing changes
+    assert_eq!(client.escalate_requests(&0u64, &10u32), 0);
+    assert_eq!(client.get_request(&request_id).urgency, UrgencyLevel::Normal);
+
+    env.ledger().set_timestamp(required_by - 11 * 3600);
+    client.escalate_requests(&0u64, &10u32);
+    assert_eq!(client.get_request(&request_id).urgency, UrgencyLevel::Urgent);
+    assert_eq!(client.get_requests_by_urgency(&UrgencyLevel::Normal).len(), 0);
+    assert_eq!(
+        client.get_requests_by_urgency(&UrgencyLevel::Urgent),
+        vec![&env, request_id]
+    );
+
+    env.ledger().set_timestamp(required_by - 3600);
+    client.escalate_requests(&0u64, &10u32);
+    assert_eq!(client.get_request(&request_id).urgency, UrgencyLevel::Critical);
+    assert_eq!(
+        client.get_requests_by_urgency(&UrgencyLevel::Critical),
+        vec![&env, request_id]
+    );
+    assert!(!client.is_request_overdue(&request_id));
+}
+
+#[test]
+fn test_escalate_requests_flags_overdue() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let required_by = 1000 + 86400;
+    let request_id = create_normal_request(&env, &client, &admin, required_by);
+
+    env.ledger().set_timestamp(required_by + 1);
+    client.escalate_requests(&0u64, &10u32);
+    client.escalate_requests(&0u64, &10u32);
+
+    assert!(client.is_request_overdue(&request_id));
+    assert_eq!(client.get_overdue_requests(), vec![&env, request_id]);
+
+    // Cancelled requests are dropped from the overdue index on the next sweep
+    client.cancel_request(&request_id, &admin);
+    client.escalate_requests(&0u64, &10u32);
+    assert!(!client.is_request_overdue(&request_id));
+}
+
+#[test]
+fn test_escalate_requests_pages_with_cursor() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let first = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let third = create_normal_request(&env, &client, &admin, 1000 + 86400);
+
+    env.ledger().set_timestamp(1000 + 86400 - 3600);
+    let cursor = client.escalate_requests(&0u64, &2u32);
+    assert_eq!(cursor, third);
+    assert_eq!(client.get_request(&first).urgency, UrgencyLevel::Critical);
+    assert_eq!(client.get_request(&second).urgency, UrgencyLevel::Critical);
+    assert_eq!(client.get_request(&third).urgency, UrgencyLevel::Normal);
+
+    assert_eq!(client.escalate_requests(&cursor, &2u32), 0);
+    assert_eq!(client.get_request(&third).urgency, UrgencyLevel::Critical);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #12)")]
+fn test_escalate_requests_invalid_limit() {
+    let (_env, _admin, client, _contract_id) = create_test_contract();
+
+    client.escalate_requests(&0u64, &0u32);
+}
+
+// ========== Request Expiry Tests ==========
+
+#[test]
+fn test_expire_requests_moves_overdue_requests_to_expired() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let overdue = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let later = create_normal_request(&env, &client, &admin, 1000 + 2 * 86400);
+    client.assign_blood_units(&overdue, &vec![&env, 7u64, 8u64]);
+
+    env.ledger().set_timestamp(1000 + 86400);
+    assert_eq!(client.expire_requests(&0u64, &10u32), 0);
+
+    let request = client.get_request(&overdue);
+    assert_eq!(request.status, RequestStatus::Expired);
+    assert_eq!(request.assigned_units.len(), 0);
+    assert_eq!(client.get_request(&later).status, RequestStatus::Pending);
+
+    assert_eq!(
+        client.get_requests_by_status(&RequestStatus::Expired),
+        vec![&env, overdue]
+    );
+    assert_eq!(
+        client.get_requests_by_status(&RequestStatus::Pending),
+        vec![&env, later]
+    );
+}
+
+#[test]
+fn test_expire_requests_pages_with_cursor() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let first = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+
+    env.ledger().set_timestamp(1000 + 86400);
+    assert_eq!(client.expire_requests(&0u64, &1u32), second);
+    assert_eq!(
+        client.get_requests_by_status(&RequestStatus::Expired),
+        vec![&env, first]
+    );
+
+    assert_eq!(client.expire_requests(&second, &1u32), 0);
+    assert_eq!(client.get_requests_by_status(&RequestStatus::Pending).len(), 0);
+}
+
+#[test]
+fn test_get_request_expires_lazily() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &admin, 1000 + 86400);
+
+    env.ledger().set_timestamp(1000 + 86400 + 1);
+    assert_eq!(client.get_request(&request_id).status, RequestStatus::Expired);
+    assert_eq!(
+        client.get_requests_by_status(&RequestStatus::Expired),
+        vec![&env, request_id]
+    );
+    assert_eq!(client.query_pending_requests(&None, &None).len(), 0);
+}
+
+#[test]
+fn test_expire_requests_skips_terminal_requests() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    client.cancel_request(&request_id, &admin);
+
+    env.ledger().set_timestamp(1000 + 86400);
+    client.expire_requests(&0u64, &10u32);
+
+    assert_eq!(client.get_request(&request_id).status, RequestStatus::Cancelled);
+}
+
+// ========== Multi-line Order Tests ==========
+
+fn trauma_order_lines(env: &Env) -> Vec<OrderLineRequest> {
+    vec![
+        env,
+        OrderLineRequest {
+            product: ProductType::RedCells,
+            blood_type: BloodType::ONegative,
+            quantity_units: 4,
+        },
+        OrderLineRequest {
+            product: ProductType::Plasma,
+            blood_type: BloodType::ABPositive,
+            quantity_units: 4,
+        },
+        OrderLineRequest {
+            product: ProductType::Platelets,
+            blood_type: BloodType::OPositive,
+            quantity_units: 1,
+        },
+    ]
+}
+
+fn create_trauma_order(env: &Env, client: &RequestContractClient<'_>, hospital: &Address) -> u64 {
+    env.ledger().set_timestamp(1000);
+    client.create_order(
+        hospital,
+        &trauma_order_lines(env),
+        &UrgencyLevel::Critical,
+        &(1000 + 7200),
+        &String::from_str(env, "Trauma Bay 1"),
+    )
+}
+
+#[test]
+fn test_create_order_success() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+
+    let order_id = create_trauma_order(&env, &client, &hospital);
+
+    let order = client.get_order(&order_id);
+    assert_eq!(order.hospital_id, hospital);
+    assert_eq!(order.lines.len(), 3);
+    assert_eq!(order.status, OrderStatus::Open);
+    assert_eq!(order.lines.get(0).unwrap().product, ProductType::RedCells);
+    assert_eq!(client.get_hospital_orders(&hospital), vec![&env, order_id]);
+}
+
+#[test]
+fn test_order_status_derived_from_lines() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    let order_id = create_trauma_order(&env, &client, &hospital);
+
+    let status = client.assign_order_units(&order_id, &0u32, &vec![&env, 1u64, 2u64]);
+    assert_eq!(status, OrderStatus::PartiallyFulfilled);
+
+    client.assign_order_units(&order_id, &0u32, &vec![&env, 3u64, 4u64]);
+    client.assign_order_units(&order_id, &1u32, &vec![&env, 5u64, 6u64, 7u64, 8u64]);
+    let order = client.get_order(&order_id);
+    assert_eq!(order.lines.get(0).unwrap().status, OrderStatus::Fulfilled);
+    assert_eq!(order.lines.get(2).unwrap().status, OrderStatus::Open);
+    assert_eq!(order.status, OrderStatus::PartiallyFulfilled);
+
+    let status = client.assign_order_units(&order_id, &2u32, &vec![&env, 9u64]);
+    assert_eq!(status, OrderStatus::Fulfilled);
+}
+
+#[test]
+fn test_cancel_order_keeps_fulfilled_lines() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    let order_id = create_trauma_order(&env, &client, &hospital);
+
+    client.assign_order_units(&order_id, &2u32, &vec![&env, 9u64]);
+    client.cancel_order(&order_id, &hospital);
+
+    let order = client.get_order(&order_id);
+    assert_eq!(order.lines.get(0).unwrap().status, OrderStatus::Cancelled);
+    assert_eq!(order.lines.get(2).unwrap().status, OrderStatus::Fulfilled);
+    assert_eq!(order.status, OrderStatus::Fulfilled);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #44)")]
+fn test_assign_order_units_over_quantity() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    let order_id = create_trauma_order(&env, &client, &hospital);
+
+    client.assign_order_units(&order_id, &2u32, &vec![&env, 9u64, 10u64]);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #48)")]
+fn test_create_order_without_lines() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+
+    env.ledger().set_timestamp(1000);
+    client.create_order(
+        &hospital,
+        &Vec::new(&env),
+        &UrgencyLevel::Critical,
+        &(1000 + 7200),
+        &String::from_str(&env, "Trauma Bay 1"),
+    );
+}
+
+// ========== Cursor Pagination Tests ==========
+
+#[test]
+fn test_page_hospital_requests_follows_cursor() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let mut ids = Vec::new(&env);
+    for _ in 0..5 {
+        ids.push_back(create_normal_request(&env, &client, &admin, 1000 + 86400));
+    }
+
+    let first = client.page_hospital_requests(&admin, &None, &0u64, &Some(2u32));
+    assert_eq!(first.requests.len(), 2);
+    assert_eq!(first.requests.get(0).unwrap().id, ids.get(0).unwrap());
+    assert_eq!(first.next_cursor, ids.get(2).unwrap());
+
+    let second = client.page_hospital_requests(&admin, &None, &first.next_cursor, &Some(2u32));
+    assert_eq!(second.requests.get(0).unwrap().id, ids.get(2).unwrap());
+
+    let last = client.page_hospital_requests(&admin, &None, &second.next_cursor, &Some(2u32));
+    assert_eq!(last.requests.len(), 1);
+    assert_eq!(last.next_cursor, 0);
+}
+
+#[test]
+fn test_page_requests_by_status_stays_sorted_after_transitions() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let first = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let third = create_normal_request(&env, &client, &admin, 1000 + 86400);
+
+    // Cancel out of order so the Cancelled index receives IDs 3 then 1
+    client.cancel_request(&third, &admin);
+    client.cancel_request(&first, &admin);
+
+    let page = client.page_requests_by_status(&RequestStatus::Cancelled, &0u64, &Some(1u32));
+    assert_eq!(page.requests.get(0).unwrap().id, first);
+    assert_eq!(page.next_cursor, third);
+
+    let pending = client.page_requests_by_status(&RequestStatus::Pending, &0u64, &None);
+    assert_eq!(pending.requests.len(), 1);
+    assert_eq!(pending.requests.get(0).unwrap().id, second);
+    assert_eq!(pending.next_cursor, 0);
+}
+
+#[test]
+fn test_page_requests_by_status_spans_index_buckets() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let mut ids = Vec::new(&env);
+    for _ in 0..70 {
+        ids.push_back(create_normal_request(&env, &client, &admin, 1000 + 86400));
+    }
+
+    let first = client.page_requests_by_status(&RequestStatus::Pending, &0u64, &Some(50u32));
+    assert_eq!(first.requests.len(), 50);
+    assert_eq!(first.next_cursor, ids.get(50).unwrap());
+
+    let second =
+        client.page_requests_by_status(&RequestStatus::Pending, &first.next_cursor, &Some(50u32));
+    assert_eq!(second.requests.len(), 20);
+    assert_eq!(second.next_cursor, 0);
+
+    // Emptying the upper bucket leaves only the lower one to walk
+    for request_id in ids.iter().filter(|id| *id >= 64) {
+        client.cancel_request(&request_id, &admin);
+    }
+    let page = client.page_requests_by_status(&RequestStatus::Pending, &60u64, &Some(50u32));
+    assert_eq!(page.requests.len(), 4);
+    assert_eq!(page.next_cursor, 0);
+    assert_eq!(
+        client
+            .get_requests_by_status(&RequestStatus::Cancelled)
+            .len(),
+        7
+    );
+    assert_eq!(client.get_hospital_requests(&admin), ids);
+}
+
+#[test]
+fn test_page_requests_by_date_range() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    let mut ids = Vec::new(&env);
+    for day in 0..4u64 {
+        let now = 1000 + day * 86400;
+        env.ledger().set_timestamp(now);
+        ids.push_back(create_normal_request(&env, &client, &admin, now + 86400));
+    }
+
+    let start = 1000 + 86400;
+    let end = 1000 + 2 * 86400;
+    let page = client.page_requests_by_date_range(&start, &end, &None, &0u64, &Some(1u32));
+    assert_eq!(page.requests.get(0).unwrap().id, ids.get(1).unwrap());
+    assert_eq!(page.next_cursor, ids.get(2).unwrap());
+
+    let page =
+        client.page_requests_by_date_range(&start, &end, &None, &page.next_cursor, &Some(1u32));
+    assert_eq!(page.requests.get(0).unwrap().id, ids.get(2).unwrap());
+    assert_eq!(page.next_cursor, 0);
+}
+
+fn last_envelope(env: &Env) -> (Symbol, EventEnvelope) {
+    let (_, topics, data) = env.events().all().last().unwrap();
+    let topic = Symbol::try_from_val(env, &topics.get(0).unwrap()).unwrap();
+    (topic, EventEnvelope::try_from_val(env, &data).unwrap())
+}
+
+#[test]
+fn test_assign_blood_units_emits_envelope() {
+    let (env, admin, client, _) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &hospital, 1000 + 2 * 86400);
+
+    client.assign_blood_units(&request_id, &vec![&env, 7u64, 9u64]);
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "units_assigned"));
+    assert_eq!(envelope.version, EVENT_SCHEMA_VERSION);
+    assert_eq!(envelope.entity_type, Symbol::new(&env, "request"));
+    assert_eq!(envelope.entity_id, EntityId::Record(request_id));
+    assert_eq!(envelope.actor, Some(admin));
+    assert_eq!(envelope.timestamp, 1000);
+    match envelope.payload {
+        EventPayload::UnitsAssigned(event) => {
+            assert_eq!(event.request_id, request_id);
+            assert_eq!(event.unit_ids, vec![&env, 7u64, 9u64]);
+            assert_eq!(event.assigned_at, 1000);
+        }
+        _ => panic!("unexpected payload"),
+    }
+}
+
+#[test]
+fn test_authorize_and_revoke_hospital_emit_envelope() {
+    let (env, admin, client, _) = create_test_contract();
+    let hospital = Address::generate(&env);
+
+    client.authorize_hospital(&hospital);
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "hospital_authorized"));
+    assert_eq!(envelope.version, EVENT_SCHEMA_VERSION);
+    assert_eq!(envelope.entity_type, Symbol::new(&env, "hospital"));
+    assert_eq!(envelope.entity_id, EntityId::Account(hospital.clone()));
+    assert_eq!(envelope.actor, Some(admin.clone()));
+    match envelope.payload {
+        EventPayload::HospitalAuthorization(event) => assert!(event.authorized),
+        _ => panic!("unexpected payload"),
+    }
+
+    client.revoke_hospital(&hospital);
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "hospital_revoked"));
+    match envelope.payload {
+        EventPayload::HospitalAuthorization(event) => assert!(!event.authorized),
+        _ => panic!("unexpected payload"),
+    }
+}
+
+#[test]
+fn test_request_events_carry_actor_and_entity() {
+    let (env, _admin, client, _) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &hospital, 1000 + 2 * 86400);
+
+    client.cancel_request(&request_id, &hospital);
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "status_changed"));
+    assert_eq!(envelope.entity_type, Symbol::new(&env, "request"));
+    assert_eq!(envelope.entity_id, EntityId::Record(request_id));
+    assert_eq!(envelope.actor, Some(hospital));
+    assert_eq!(envelope.timestamp, 1000);
+    match envelope.payload {
+        EventPayload::RequestStatusChanged(event) => {
+            assert_eq!(event.new_status, RequestStatus::Cancelled)
+        }
+        _ => panic!("unexpected payload"),
+    }
+}
+
+// ========== Invariant Checker Tests ==========
+
+fn issue(index: IndexKind, kind: IssueKind, request_id: u64) -> IndexIssue {
+    IndexIssue {
+        index,
+        kind,
+        request_id,
+        expected: 0,
+        actual: 0,
+    }
+}
+
+#[test]
+fn test_verify_invariants_clean_requests_pages() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let first = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let third = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    client.approve_request(&first);
+    client.cancel_request(&second, &admin);
+
+    let report = client.verify_invariants(&0u64, &2u32);
+    assert!(report.issues.is_empty());
+    assert_eq!(report.next_cursor, third);
+
+    let report = client.verify_invariants(&report.next_cursor, &2u32);
+    assert!(report.issues.is_empty());
+    assert_eq!(report.next_cursor, 0);
+}
+
+#[test]
+fn test_verify_invariants_reports_drift_and_repair_fixes_it() {
+    let (env, admin, client, contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    let first = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    client.cancel_request(&second, &admin);
+
+    env.as_contract(&contract_id, || {
+        storage::set_index(
+            &env,
+            &DataKey::UrgencyIndex(UrgencyLevel::Normal),
+            &vec![&env, second],
+        );
+        storage::set_index(
+            &env,
+            &DataKey::HospitalIndex(admin.clone()),
+            &vec![&env, second, first],
+        );
+        storage::set_index(
+            &env,
+            &DataKey::StatusIndex(RequestStatus::Pending),
+            &vec![&env, first, second],
+        );
+        storage::set_index(&env, &DataKey::OverdueIndex, &vec![&env, second]);
+        storage::set_index(
+            &env,
+            &DataKey::StatusIndex(RequestStatus::Expired),
+            &vec![&env, 9u64],
+        );
+    });
+
+    let expected = vec![
+        &env,
+        issue(IndexKind::Urgency, IssueKind::MissingEntry, first),
+        issue(IndexKind::Hospital, IssueKind::Unsorted, 0),
+        issue(IndexKind::Status, IssueKind::StaleEntry, second),
+        issue(IndexKind::Overdue, IssueKind::StaleEntry, second),
+        issue(IndexKind::Status, IssueKind::DanglingEntry, 9),
+    ];
+    let report = client.verify_invariants(&0u64, &10u32);
+    assert_eq!(report.issues, expected);
+    assert_eq!(report.next_cursor, 0);
+
+    let report = client.repair_indexes(&0u64, &10u32);
+    assert_eq!(report.issues, expected);
+    assert_eq!(env.auths()[0].0, admin);
+
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "indexes_repaired"));
+    assert_eq!(envelope.actor, Some(admin.clone()));
+    match envelope.payload {
+        EventPayload::IndexesRepaired(event) => {
+            assert_eq!(event.from_id, 1);
+            assert_eq!(event.to_id, second);
+            assert_eq!(event.issues_fixed, 5);
+        }
+        _ => panic!("unexpected payload"),
+    }
+
+    assert!(client.verify_invariants(&0u64, &10u32).issues.is_empty());
+    assert_eq!(
+        client.get_requests_by_urgency(&UrgencyLevel::Normal),
+        vec![&env, first, second]
+    );
+    assert_eq!(
+        client.get_requests_by_status(&RequestStatus::Pending),
+        vec![&env, first]
+    );
+    assert_eq!(
+        client.get_hospital_requests(&admin),
+        vec![&env, first, second]
+    );
+    assert!(client.get_overdue_requests().is_empty());
+}
+
+#[test]
+fn test_verify_invariants_detects_lagging_request_counter() {
+    let (env, admin, client, contract_id) = create_test_contract();
+
+    env.ledger().set_timestamp(1000);
+    create_normal_request(&env, &client, &admin, 1000 + 86400);
+    let second = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    env.as_contract(&contract_id, || storage::set_request_counter(&env, 1));
+
+    let report = client.verify_invariants(&0u64, &10u32);
+    assert_eq!(
+        report.issues,
+        vec![
+            &env,
+            IndexIssue {
+                index: IndexKind::RequestCounter,
+                kind: IssueKind::CounterMismatch,
+                request_id: 0,
+                expected: second,
+                actual: 1,
+            },
+        ]
+    );
+
+    client.repair_indexes(&0u64, &10u32);
+    let third = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    assert_eq!(third, second + 1);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #12)")]
+fn test_verify_invariants_invalid_limit() {
+    let (_env, _admin, client, _contract_id) = create_test_contract();
+
+    client.verify_invariants(&0u64, &101u32);
+}
+
+// ========== Pause Tests ==========
+
+#[test]
+fn test_pause_blocks_only_the_paused_family() {
+    let (env, admin, client, contract_id) = create_test_contract();
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &admin, 1000 + 86400);
+    client.approve_request(&request_id);
+
+    let reason = String::from_str(&env, "allocation bug under investigation");
+    client.pause(&admin, &PauseScope::Allocation, &reason);
+
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "paused"));
+    assert_eq!(envelope.entity_id, EntityId::Account(contract_id));
+    assert_eq!(envelope.actor, Some(admin.clone()));
+    match envelope.payload {
+        EventPayload::PauseChanged(event) => {
+            assert_eq!(event.scope, PauseScope::Allocation);
+            assert!(event.paused);
+            assert_eq!(event.reason, reason);
+        }
+        _ => panic!("unexpected payload"),
+    }
+
+    assert_eq!(
+        client.try_assign_blood_units(&request_id, &vec![&env, 7u64]),
+        Err(Ok(ContractError::ContractPaused))
+    );
+    assert!(client.is_paused(&PauseScope::Allocation));
+    assert!(!client.is_paused(&PauseScope::Requests));
+
+    // Other families and reads keep working
+    create_normal_request(&env, &client, &admin, 1000 + 86400);
+    assert_eq!(
+        client.get_request(&request_id).status,
+        RequestStatus::Approved
+    );
+
+    client.unpause(
+        &admin,
+        &PauseScope::Allocation,
+        &String::from_str(&env, "fixed"),
+    );
+    let (topic, _) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "unpaused"));
+    client.assign_blood_units(&request_id, &vec![&env, 7u64]);
+}
+
+#[test]
+fn test_guardian_pause_all_blocks_every_family() {
+    let (env, admin, client, _contract_id) = create_test_contract();
+    env.ledger().set_timestamp(1000);
+    let request_id = create_normal_request(&env, &client, &admin, 1000 + 86400);
+
+    let guardian = Address::generate(&env);
+    client.set_guardian(&guardian);
+    assert_eq!(client.get_guardian(), Some(guardian.clone()));
+
+    let reason = String::from_str(&env, "compromised hospital key");
+    client.pause(&guardian, &PauseScope::All, &reason);
+    assert!(client.is_paused(&PauseScope::Requests));
+
+    assert_eq!(
+        client.try_cancel_request(&request_id, &admin),
+        Err(Ok(ContractError::ContractPaused))
+    );
+    assert_eq!(
+        client.try_create_order(
+            &admin,
+            &trauma_order_lines(&env),
+            &UrgencyLevel::Urgent,
+            &(1000 + 86400),
+            &String::from_str(&env, "Ward 3"),
+        ),
+        Err(Ok(ContractError::ContractPaused))
+    );
+
+    // Expiry keeps running while paused
+    env.ledger().set_timestamp(1000 + 86400 + 1);
+    assert_eq!(client.expire_requests(&0u64, &10u32), 0);
+    assert_eq!(
+        client.get_request(&request_id).status,
+        RequestStatus::Expired
+    );
+
+    // Only the admin can lift a pause
+    assert_eq!(
+        client.try_unpause(&guardian, &PauseScope::All, &reason),
+        Err(Ok(ContractError::Unauthorized))
+    );
+    client.unpause(&admin, &PauseScope::All, &reason);
+    assert!(!client.is_paused(&PauseScope::Requests));
+}
+
+#[test]
+fn test_pause_requires_admin_or_guardian() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let stranger = Address::generate(&env);
+
+    assert_eq!(
+        client.try_pause(
+            &stranger,
+            &PauseScope::Requests,
+            &String::from_str(&env, "junk requests")
+        ),
+        Err(Ok(ContractError::Unauthorized))
+    );
+    assert!(!client.is_paused(&PauseScope::Requests));
+}
+
+// ========== Rate Limit Tests ==========
+
+#[test]
+fn test_rate_limit_window_rejects_and_resets() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    env.ledger().set_timestamp(1000);
+
+    let limit = RateLimit {
+        max_requests: 2,
+        window_secs: 3600,
+        max_open: 0,
+    };
+    client.set_rate_limit(&hospital, &limit);
+    assert_eq!(client.get_rate_limit(&hospital), Some(limit));
+
+    let required_by = 1000 + 2 * 86400;
+    create_normal_request(&env, &client, &hospital, required_by);
+    create_normal_request(&env, &client, &hospital, required_by);
+    assert_eq!(
+        client.try_create_request(
+            &hospital,
+            &BloodType::OPositive,
+            &450u32,
+            &UrgencyLevel::Normal,
+            &required_by,
+            &String::from_str(&env, "Hospital"),
+            &Address::generate(&env),
+            &String::from_str(&env, "Surgery"),
+            &String::from_str(&env, "Notes"),
+        ),
+        Err(Ok(ContractError::RequestLimitExceeded))
+    );
+
+    // A new window starts once the old one has passed
+    env.ledger().set_timestamp(1000 + 3600);
+    create_normal_request(&env, &client, &hospital, required_by);
+    assert_eq!(client.get_over_limit_requests().len(), 0);
+}
+
+#[test]
+fn test_open_request_cap_frees_on_cancel() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    env.ledger().set_timestamp(1000);
+
+    client.set_rate_limit(
+        &hospital,
+        &RateLimit {
+            max_requests: 0,
+            window_secs: 0,
+            max_open: 1,
+        },
+    );
+
+    let required_by = 1000 + 2 * 86400;
+    let first = create_normal_request(&env, &client, &hospital, required_by);
+    assert_eq!(
+        client.try_create_request(
+            &hospital,
+            &BloodType::OPositive,
+            &450u32,
+            &UrgencyLevel::Urgent,
+            &required_by,
+            &String::from_str(&env, "Hospital"),
+            &Address::generate(&env),
+            &String::from_str(&env, "Surgery"),
+            &String::from_str(&env, "Notes"),
+        ),
+        Err(Ok(ContractError::RequestLimitExceeded))
+    );
+
+    client.cancel_request(&first, &hospital);
+    create_normal_request(&env, &client, &hospital, required_by);
+}
+
+#[test]
+fn test_critical_request_over_limit_is_flagged() {
+    let (env, _admin, client, _contract_id) = create_test_contract();
+    let hospital = setup_authorized_hospital(&env, &client);
+    env.ledger().set_timestamp(1000);
+
+    client.set_rate_limit(
+        &hospital,
+        &RateLimit {
+            max_requests: 1,
+            window_secs: 3600,
+            max_open: 0,
+        },
+    );
+
+    create_normal_request(&env, &client, &hospital, 1000 + 2 * 86400);
+    let request_id = client.create_request(
+        &hospital,
+        &BloodType::ONegative,
+        &900u32,
+        &UrgencyLevel::Critical,
+        &(1000 + 7200),
+        &String::from_str(&env, "ER"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Trauma"),
+        &String::from_str(&env, "Notes"),
+    );
+
+    let (topic, envelope) = last_envelope(&env);
+    assert_eq!(topic, Symbol::new(&env, "request_over_limit"));
+    assert_eq!(envelope.entity_id, EntityId::Record(request_id));
+    match envelope.payload {
+        EventPayload::RequestOverLimit(event) => {
+            assert_eq!(event.hospital_id, hospital);
+            assert_eq!(event.window_requests, 2);
+            assert_eq!(event.open_requests, 2);
+        }
+        _ => panic!("unexpected payload"),
+    }
+
+    assert_eq!(client.get_over_limit_requests(), vec![&env, request_id]);
+    client.clear_over_limit_flag(&request_id);
+    assert_eq!(client.get_over_limit_requests().len(), 0);
+    assert_eq!(
+        client.try_clear_over_limit_flag(&request_id),
+        Err(Ok(ContractError::NotFound))
+    );
+}
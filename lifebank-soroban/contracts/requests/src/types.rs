@@ -23,19 +23,6 @@ pub enum BloodType {
     /// Type O positive (O+)
     OPositive,
     /// Type O negative (O-) - Universal blood donor
-use soroban_sdk::{contracttype, Address, Vec};
-
-/// Blood type enumeration supporting all major blood groups
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq, Copy)]
-pub enum BloodType {
-    APositive,
-    ANegative,
-    BPositive,
-    BNegative,
-    ABPositive,
-    ABNegative,
-    OPositive,
     ONegative,
 }
 
@@ -84,52 +71,6 @@ pub enum RequestStatus {
     Expired,
 }
 
-/// Complete blood request record from a hospital
-///
-/// Represents a request for blood units with full tracking information
-/// from creation through delivery or cancellation.
-///
-/// # Storage Keys
-/// - Primary key: `id` (u64)
-/// - Secondary indexes: `hospital_id`, `blood_type`, `status`, `urgency`
-/// Determines priority and fulfillment timeline:
-/// - Critical: Life-threatening, immediate fulfillment required
-/// - Urgent: High priority, fulfillment within hours
-/// - Normal: Standard priority, fulfillment within days
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq, Copy, PartialOrd, Ord)]
-pub enum UrgencyLevel {
-    /// Life-threatening situation, immediate fulfillment required
-    Critical,
-    /// High priority, fulfillment within hours
-    Urgent,
-    /// Standard priority, fulfillment within days
-    Normal,
-}
-
-/// Request status representing its lifecycle
-///
-/// Status transitions:
-/// Pending -> Approved -> Fulfilled -> Completed
-///        \-> Rejected
-///        \-> Cancelled
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq, Copy)]
-pub enum RequestStatus {
-    /// Initial state, awaiting approval
-    Pending,
-    /// Approved by blood bank, awaiting fulfillment
-    Approved,
-    /// Blood units assigned and being prepared
-    Fulfilled,
-    /// Request completed successfully
-    Completed,
-    /// Request rejected by blood bank
-    Rejected,
-    /// Request cancelled by hospital
-    Cancelled,
-}
-
 /// Request metadata containing additional context
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -210,38 +151,6 @@ impl UrgencyLevel {
     pub fn is_higher_than(&self, other: &UrgencyLevel) -> bool {
         self.priority_weight() > other.priority_weight()
     }
-    /// Hospital address requesting blood
-    pub hospital_id: Address,
-
-    /// Blood type requested
-    pub blood_type: BloodType,
-
-    /// Quantity requested in milliliters
-    pub quantity_ml: u32,
-
-    /// Urgency level of the request
-    pub urgency: UrgencyLevel,
-
-    /// Current status of the request
-    pub status: RequestStatus,
-
-    /// Unix timestamp when request was created
-    pub created_at: u64,
-
-    /// Unix timestamp when blood is required by
-    pub required_by: u64,
-
-    /// Unix timestamp when request was fulfilled (if applicable)
-    pub fulfilled_at: Option<u64>,
-
-    /// Vector of blood unit IDs assigned to this request
-    pub assigned_units: Vec<u64>,
-
-    /// Delivery address for the blood units
-    pub delivery_address: soroban_sdk::String,
-
-    /// Request metadata (patient info, procedure, notes)
-    pub metadata: RequestMetadata,
 }
 
 impl RequestStatus {
@@ -315,9 +224,6 @@ impl RequestStatus {
                 | RequestStatus::Fulfilled
                 | RequestStatus::InDelivery
         )
-    /// Check if this status is a terminal state
-    pub fn is_terminal(&self) -> bool {
-        matches!(self, RequestStatus::Rejected | RequestStatus::Completed | RequestStatus::Cancelled)
     }
 }
 
@@ -364,40 +270,6 @@ impl BloodRequest {
             if fulfilled < self.created_at {
                 return Err(ContractError::InvalidTimestamp);
             }
-    /// Validate the blood request
-    ///
-    /// Checks:
-    /// - Quantity is within acceptable range (50-5000ml)
-    /// - Required_by is in the future
-    /// - Required_by is reasonable relative to created_at
-    /// - Delivery address is not empty
-    pub fn validate(&self, current_time: u64) -> Result<(), crate::error::ContractError> {
-        use crate::error::ContractError;
-
-        // Validate quantity (50-5000ml for hospital requests)
-        if self.quantity_ml < 50 || self.quantity_ml > 5000 {
-            return Err(ContractError::InvalidQuantity);
-        }
-
-        // Required_by must be in the future
-        if self.required_by <= current_time {
-            return Err(ContractError::InvalidTimestamp);
-        }
-
-        // Required_by should be reasonable (not more than 30 days in future)
-        let max_future = current_time + (30 * 86400);
-        if self.required_by > max_future {
-            return Err(ContractError::InvalidTimestamp);
-        }
-
-        // Created_at should be before required_by
-        if self.created_at >= self.required_by {
-            return Err(ContractError::InvalidTimestamp);
-        }
-
-        // Delivery address should not be empty
-        if self.delivery_address.len() == 0 {
-            return Err(ContractError::InvalidInput);
         }
 
         Ok(())
@@ -416,7 +288,166 @@ impl BloodRequest {
 
     /// Check if the request has been fulfilled (has assigned units)
     pub fn has_assigned_units(&self) -> bool {
-        self.assigned_units.len() > 0
+        !self.assigned_units.is_empty()
+    }
+
+    /// Check if request has exceeded its required_by deadline
+    pub fn is_overdue(&self, current_time: u64) -> bool {
+        current_time > self.required_by
+    }
+
+    /// Check if request can be fulfilled based on urgency and time
+    pub fn can_fulfill(&self, current_time: u64) -> bool {
+        !self.is_overdue(current_time) && self.status == RequestStatus::Approved
+    }
+}
+
+/// Blood product carried by an order line
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq, Copy)]
+pub enum ProductType {
+    /// Whole blood
+    WholeBlood,
+    /// Packed red blood cells
+    RedCells,
+    /// Fresh frozen plasma
+    Plasma,
+    /// Platelet pool
+    Platelets,
+    /// Cryoprecipitate
+    Cryoprecipitate,
+}
+
+/// Fulfillment state shared by order lines and whole orders
+///
+/// An order's status is derived from its lines, see `BloodOrder::derive_status`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq, Copy)]
+pub enum OrderStatus {
+    /// No units assigned yet
+    Open,
+    /// Some but not all units assigned
+    PartiallyFulfilled,
+    /// All requested units assigned
+    Fulfilled,
+    /// Cancelled by hospital or admin
+    Cancelled,
+}
+
+/// Line item submitted when creating an order
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OrderLineRequest {
+    /// Requested blood product
+    pub product: ProductType,
+
+    /// Requested blood type
+    pub blood_type: BloodType,
+
+    /// Number of units (or pools, for platelets) requested
+    pub quantity_units: u32,
+}
+
+/// Line item of a blood order with its own fulfillment state
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OrderLine {
+    /// Requested blood product
+    pub product: ProductType,
+
+    /// Requested blood type
+    pub blood_type: BloodType,
+
+    /// Number of units requested
+    pub quantity_units: u32,
+
+    /// IDs of blood units assigned to this line
+    pub assigned_units: Vec<u64>,
+
+    /// Fulfillment state of this line
+    pub status: OrderStatus,
+}
+
+/// Multi-line blood order from a hospital
+///
+/// Groups several products and blood types under one deadline and
+/// delivery address, e.g. red cells, plasma and platelets for a trauma case.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct BloodOrder {
+    /// Unique identifier for this order
+    pub id: u64,
+
+    /// Hospital address that submitted the order
+    pub hospital_id: Address,
+
+    /// Urgency level shared by all lines
+    pub urgency: UrgencyLevel,
+
+    /// Unix timestamp (seconds) when order was created
+    pub created_at: u64,
+
+    /// Unix timestamp (seconds) by which blood is required
+    pub required_by: u64,
+
+    /// Physical delivery address for the blood units
+    pub delivery_address: String,
+
+    /// Line items of the order
+    pub lines: Vec<OrderLine>,
+
+    /// Overall status, derived from the lines
+    pub status: OrderStatus,
+}
+
+impl OrderLine {
+    /// Status of the line given how many units have been assigned
+    pub fn fill_status(&self) -> OrderStatus {
+        let assigned = self.assigned_units.len();
+        if assigned == 0 {
+            OrderStatus::Open
+        } else if assigned < self.quantity_units {
+            OrderStatus::PartiallyFulfilled
+        } else {
+            OrderStatus::Fulfilled
+        }
+    }
+}
+
+impl BloodOrder {
+    /// Derive the order's status from its lines
+    ///
+    /// - Cancelled if every line is cancelled
+    /// - Fulfilled if every remaining line is fulfilled
+    /// - PartiallyFulfilled if any remaining line has units assigned
+    /// - Open otherwise
+    pub fn derive_status(&self) -> OrderStatus {
+        let mut active = 0u32;
+        let mut fulfilled = 0u32;
+        let mut started = false;
+
+        for line in self.lines.iter() {
+            if line.status == OrderStatus::Cancelled {
+                continue;
+            }
+            active += 1;
+            if line.status == OrderStatus::Fulfilled {
+                fulfilled += 1;
+            }
+            if !line.assigned_units.is_empty() {
+                started = true;
+            }
+        }
+
+        if active == 0 {
+            OrderStatus::Cancelled
+        } else if fulfilled == active {
+            OrderStatus::Fulfilled
+        } else if started {
+            OrderStatus::PartiallyFulfilled
+        } else {
+            OrderStatus::Open
+        }
     }
 }
 
@@ -447,6 +478,33 @@ pub enum DataKey {
 
     /// Authorized hospitals set
     AuthorizedHospital(Address),
+
+    /// Index: Vec<u64> of requests flagged past their deadline
+    OverdueIndex,
+
+    /// Individual blood order by ID
+    BloodOrder(u64),
+
+    /// Counter for generating new order IDs
+    OrderCounter,
+
+    /// Index: Hospital ID -> Vec<u64> (order IDs)
+    HospitalOrderIndex(Address),
+
+    /// Guardian who may pause operations alongside the admin
+    Guardian,
+
+    /// Set while an operation family is paused
+    Paused(PauseScope),
+
+    /// Request limits set for a hospital
+    RateLimit(Address),
+
+    /// Requests a hospital has created in its current rate window
+    RequestWindow(Address),
+
+    /// Index: Vec<u64> of critical requests created over their hospital's limits
+    OverLimitIndex,
 }
 
 /// Event emitted when a new blood request is created
@@ -502,13 +560,379 @@ pub struct UnitsAssignedEvent {
     /// IDs of assigned blood units
     pub unit_ids: Vec<u64>,
 
-    /// Total quantity assigned in milliliters
-    pub total_quantity_ml: u32,
-
     /// Timestamp when units were assigned
     pub assigned_at: u64,
 }
 
+/// One page of blood requests from a cursor-paginated query
+///
+/// Pass `next_cursor` back as `cursor` to fetch the following page.
+/// A `next_cursor` of 0 means there are no more pages.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RequestPage {
+    /// Requests on this page, in ascending ID order
+    pub requests: Vec<BloodRequest>,
+
+    /// First request ID of the next page, or 0 when done
+    pub next_cursor: u64,
+}
+
+/// One page of blood orders from a cursor-paginated query
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OrderPage {
+    /// Orders on this page, in ascending ID order
+    pub orders: Vec<BloodOrder>,
+
+    /// First order ID of the next page, or 0 when done
+    pub next_cursor: u64,
+}
+
+/// Operation families that can be paused on their own
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseScope {
+    /// Every family below
+    All,
+    /// Assigning blood units to requests and order lines
+    Allocation,
+    /// Creating, approving, updating and cancelling requests and orders
+    Requests,
+}
+
+/// Event emitted when the admin appoints a guardian
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct GuardianSetEvent {
+    /// Address that may now pause operations
+    pub guardian: Address,
+}
+
+/// Event emitted when an operation family is paused or unpaused
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct PauseChangedEvent {
+    /// Family the change applies to
+    pub scope: PauseScope,
+
+    /// Whether the family is now paused
+    pub paused: bool,
+
+    /// Why the pause was raised or lifted
+    pub reason: String,
+}
+
+/// Request limits for one hospital; a zero field disables that limit
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Requests allowed per window
+    pub max_requests: u32,
+
+    /// Window length in seconds
+    pub window_secs: u64,
+
+    /// Pending or approved requests allowed at once
+    pub max_open: u32,
+}
+
+/// Requests a hospital has created in its current rate window
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RequestWindow {
+    /// When the window started
+    pub start: u64,
+
+    /// Requests created since `start`
+    pub count: u32,
+}
+
+/// Event emitted when the admin sets a hospital's request limits
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RateLimitSetEvent {
+    /// Hospital the limits apply to
+    pub hospital_id: Address,
+
+    /// New limits
+    pub limit: RateLimit,
+}
+
+/// Event emitted when a critical request is created over its hospital's limits
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RequestOverLimitEvent {
+    /// Request flagged for review
+    pub request_id: u64,
+
+    /// Hospital that created the request
+    pub hospital_id: Address,
+
+    /// Requests in the current window, including this one
+    pub window_requests: u32,
+
+    /// Open requests, including this one
+    pub open_requests: u32,
+}
+
+/// Record, index or counter checked by `verify_invariants`
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IndexKind {
+    /// The blood request record itself
+    Record,
+    /// `HospitalIndex`
+    Hospital,
+    /// `BloodTypeIndex`
+    BloodType,
+    /// `StatusIndex`
+    Status,
+    /// `UrgencyIndex`
+    Urgency,
+    /// `OverdueIndex`
+    Overdue,
+    /// `RequestCounter`
+    RequestCounter,
+}
+
+/// Kind of mismatch between records, indexes and counters
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IssueKind {
+    /// An ID at or below the counter has no record
+    MissingRecord,
+    /// The record is missing from an index it belongs in
+    MissingEntry,
+    /// An index lists the record under a key that no longer matches it
+    StaleEntry,
+    /// An index lists an ID that has no record
+    DanglingEntry,
+    /// An index is not in strictly ascending ID order
+    Unsorted,
+    /// A counter disagrees with the records
+    CounterMismatch,
+}
+
+/// One inconsistency found by `verify_invariants`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexIssue {
+    /// Record, index or counter the issue is in
+    pub index: IndexKind,
+
+    /// What is wrong
+    pub kind: IssueKind,
+
+    /// Request ID concerned (0 for whole-index issues)
+    pub request_id: u64,
+
+    /// Value the counter should hold (`CounterMismatch` only)
+    pub expected: u64,
+
+    /// Value the counter holds (`CounterMismatch` only)
+    pub actual: u64,
+}
+
+/// One page of invariant checks
+///
+/// Pass `next_cursor` back as `cursor` to check the following page.
+/// A `next_cursor` of 0 means every request has been checked.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvariantReport {
+    /// Issues found on this page; for `repair_indexes`, the issues fixed
+    pub issues: Vec<IndexIssue>,
+
+    /// First request ID of the next page, or 0 when done
+    pub next_cursor: u64,
+}
+
+/// Event emitted when the admin repairs index drift
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct IndexesRepairedEvent {
+    /// First request ID of the repaired page
+    pub from_id: u64,
+
+    /// Last request ID of the repaired page
+    pub to_id: u64,
+
+    /// Number of issues fixed
+    pub issues_fixed: u32,
+}
+
+/// Event emitted when the admin authorizes or revokes a hospital
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct HospitalAuthorizationEvent {
+    /// Hospital whose authorization changed
+    pub hospital: Address,
+
+    /// Whether the hospital may now create requests
+    pub authorized: bool,
+}
+
+/// Event emitted when the contract is initialized
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct InitializedEvent {
+    /// Admin address set at initialization
+    pub admin: Address,
+}
+
+/// Version of the `EventEnvelope` layout; bump on incompatible payload changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Identifier of the entity an event is about
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntityId {
+    /// Numeric record id (request, order)
+    Record(u64),
+
+    /// Account address (admin, hospital)
+    Account(Address),
+}
+
+/// Typed payload carried by an `EventEnvelope`
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum EventPayload {
+    Initialized(InitializedEvent),
+    HospitalAuthorization(HospitalAuthorizationEvent),
+    RequestCreated(RequestCreatedEvent),
+    RequestStatusChanged(RequestStatusChangedEvent),
+    UnitsAssigned(UnitsAssignedEvent),
+    UnitsReleased(UnitsReleasedEvent),
+    UrgencyEscalated(UrgencyEscalatedEvent),
+    RequestOverdue(RequestOverdueEvent),
+    OrderCreated(OrderCreatedEvent),
+    OrderLineUpdated(OrderLineUpdatedEvent),
+    IndexesRepaired(IndexesRepairedEvent),
+    GuardianSet(GuardianSetEvent),
+    PauseChanged(PauseChangedEvent),
+    RateLimitSet(RateLimitSetEvent),
+    RequestOverLimit(RequestOverLimitEvent),
+}
+
+/// Common envelope wrapping every event published by the contract
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct EventEnvelope {
+    /// Schema version (`EVENT_SCHEMA_VERSION`)
+    pub version: u32,
+
+    /// Kind of entity the event is about ("request", "order", "hospital", "admin")
+    pub entity_type: Symbol,
+
+    /// Id of that entity
+    pub entity_id: EntityId,
+
+    /// Address that triggered the change; None for keeper sweeps
+    pub actor: Option<Address>,
+
+    /// Ledger timestamp when the event was published
+    pub timestamp: u64,
+
+    /// Typed event data
+    pub payload: EventPayload,
+}
+
+/// Event emitted when assigned blood units are released from a request
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct UnitsReleasedEvent {
+    /// Request ID
+    pub request_id: u64,
+
+    /// IDs of released blood units
+    pub unit_ids: Vec<u64>,
+
+    /// Timestamp when units were released
+    pub released_at: u64,
+}
+
+/// Event emitted when a request's urgency is raised ahead of its deadline
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct UrgencyEscalatedEvent {
+    /// Request ID
+    pub request_id: u64,
+
+    /// Urgency before escalation
+    pub old_urgency: UrgencyLevel,
+
+    /// Urgency after escalation
+    pub new_urgency: UrgencyLevel,
+
+    /// When blood is required by
+    pub required_by: u64,
+
+    /// Timestamp of the escalation
+    pub escalated_at: u64,
+}
+
+/// Event emitted when an open request passes its deadline
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RequestOverdueEvent {
+    /// Request ID
+    pub request_id: u64,
+
+    /// Hospital that created the request
+    pub hospital_id: Address,
+
+    /// When blood was required by
+    pub required_by: u64,
+
+    /// Timestamp when the request was flagged
+    pub flagged_at: u64,
+}
+
+/// Event emitted when a multi-line order is created
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OrderCreatedEvent {
+    /// Unique ID of the created order
+    pub order_id: u64,
+
+    /// Hospital that created the order
+    pub hospital_id: Address,
+
+    /// Number of line items
+    pub line_count: u32,
+
+    /// Urgency level
+    pub urgency: UrgencyLevel,
+
+    /// When blood is required by
+    pub required_by: u64,
+
+    /// When the order was created
+    pub created_at: u64,
+}
+
+/// Event emitted when an order line changes state
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OrderLineUpdatedEvent {
+    /// Order ID
+    pub order_id: u64,
+
+    /// Index of the line within the order
+    pub line_index: u32,
+
+    /// New status of the line
+    pub line_status: OrderStatus,
+
+    /// Derived status of the whole order
+    pub order_status: OrderStatus,
+
+    /// Timestamp of the update
+    pub updated_at: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -818,39 +1242,4 @@ mod tests {
         request.assigned_units.push_back(1);
         assert!(request.has_assigned_units());
     }
-    /// Check if request has exceeded its required_by deadline
-    pub fn is_overdue(&self, current_time: u64) -> bool {
-        current_time > self.required_by
-    }
-
-    /// Get time remaining until required_by deadline in seconds
-    /// Returns negative value if overdue
-    pub fn time_remaining(&self, current_time: u64) -> i64 {
-        self.required_by as i64 - current_time as i64
-    }
-
-    /// Check if request can be fulfilled based on urgency and time
-    pub fn can_fulfill(&self, current_time: u64) -> bool {
-        !self.is_overdue(current_time) && self.status == RequestStatus::Approved
-    }
-}
-
-/// Storage key enumeration for efficient indexing
-#[contracttype]
-#[derive(Clone, Debug)]
-pub enum DataKey {
-    /// Primary key for blood requests
-    BloodRequest(u64),
-    /// Counter for generating request IDs
-    RequestCounter,
-    /// Index by hospital ID
-    HospitalIndex(Address),
-    /// Index by blood type
-    BloodTypeIndex(BloodType),
-    /// Index by status
-    StatusIndex(RequestStatus),
-    /// Index by urgency level
-    UrgencyIndex(UrgencyLevel),
-    /// Admin address
-    Admin,
 }
@@ -0,0 +1,178 @@
+use crate::error::DecodeError;
+use crate::input::RawEvent;
+use soroban_sdk::xdr::{Limits, ReadXdr};
+use std::collections::BTreeMap;
+
+/// Contract family an event was published by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ContractKind {
+    /// `HealthChainContract` (soroban-sdk 22)
+    HealthChain,
+
+    /// Lifebank inventory contract
+    Inventory,
+
+    /// Lifebank requests contract
+    Requests,
+}
+
+/// Envelope decoded into the publishing contract's own types
+#[derive(Clone)]
+pub enum EventBody {
+    HealthChain(healthchain::EventEnvelope),
+    Inventory(inventory_contract::EventEnvelope),
+    Requests(request_contract::EventEnvelope),
+}
+
+impl EventBody {
+    /// Contract family the envelope belongs to
+    pub fn kind(&self) -> ContractKind {
+        match self {
+            EventBody::HealthChain(_) => ContractKind::HealthChain,
+            EventBody::Inventory(_) => ContractKind::Inventory,
+            EventBody::Requests(_) => ContractKind::Requests,
+        }
+    }
+
+    /// Ledger timestamp recorded in the envelope
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            EventBody::HealthChain(envelope) => envelope.timestamp,
+            EventBody::Inventory(envelope) => envelope.timestamp,
+            EventBody::Requests(envelope) => envelope.timestamp,
+        }
+    }
+}
+
+/// An exported event together with its typed envelope
+#[derive(Clone)]
+pub struct DecodedEvent {
+    pub raw: RawEvent,
+    pub body: EventBody,
+}
+
+/// Decodes raw events into the typed envelopes of the known contracts
+///
+/// Holds one offline host per soroban-sdk version; decoded addresses and
+/// symbols stay valid for as long as the decoder is alive. Register contract
+/// ids with [`Decoder::with_contract`] where known; events from unregistered
+/// contracts are matched against each contract's envelope in turn.
+pub struct Decoder {
+    env: soroban_sdk::Env,
+    hc_env: soroban_sdk_22::Env,
+    contracts: BTreeMap<String, ContractKind>,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        let env = soroban_sdk::Env::default();
+        env.cost_estimate().budget().reset_unlimited();
+        let hc_env = soroban_sdk_22::Env::default();
+        hc_env.cost_estimate().budget().reset_unlimited();
+
+        Decoder {
+            env,
+            hc_env,
+            contracts: BTreeMap::new(),
+        }
+    }
+
+    /// Pin a contract id to the contract family that published it
+    pub fn with_contract(mut self, contract_id: &str, kind: ContractKind) -> Self {
+        self.contracts.insert(contract_id.to_owned(), kind);
+        self
+    }
+
+    /// Host used for lifebank contract values
+    pub fn env(&self) -> &soroban_sdk::Env {
+        &self.env
+    }
+
+    /// Host used for `HealthChainContract` values
+    pub fn hc_env(&self) -> &soroban_sdk_22::Env {
+        &self.hc_env
+    }
+
+    /// Decode one raw event
+    ///
+    /// # Errors
+    /// - `UnknownEvent`: No contract envelope matches the value
+    /// - `UnsupportedVersion`: The envelope uses an unknown schema version
+    pub fn decode(&self, raw: RawEvent) -> Result<DecodedEvent, DecodeError> {
+        let pinned = raw
+            .contract_id
+            .as_ref()
+            .and_then(|id| self.contracts.get(id))
+            .copied();
+
+        let body = match pinned {
+            Some(kind) => self.decode_as(kind, &raw.value)?,
+            None => [
+                ContractKind::HealthChain,
+                ContractKind::Inventory,
+                ContractKind::Requests,
+            ]
+            .into_iter()
+            .find_map(|kind| self.decode_as(kind, &raw.value).ok())
+            .ok_or_else(|| DecodeError::UnknownEvent(raw.topics.join("/")))?,
+        };
+
+        let version = match &body {
+            EventBody::HealthChain(envelope) => {
+                (envelope.version, healthchain::EVENT_SCHEMA_VERSION)
+            }
+            EventBody::Inventory(envelope) => {
+                (envelope.version, inventory_contract::EVENT_SCHEMA_VERSION)
+            }
+            EventBody::Requests(envelope) => {
+                (envelope.version, request_contract::EVENT_SCHEMA_VERSION)
+            }
+        };
+        if version.0 != version.1 {
+            return Err(DecodeError::UnsupportedVersion(version.0));
+        }
+
+        Ok(DecodedEvent { raw, body })
+    }
+
+    /// Decode every event of a dump, stopping at the first failure
+    pub fn decode_all(&self, raws: Vec<RawEvent>) -> Result<Vec<DecodedEvent>, DecodeError> {
+        raws.into_iter().map(|raw| self.decode(raw)).collect()
+    }
+
+    fn decode_as(&self, kind: ContractKind, value: &[u8]) -> Result<EventBody, DecodeError> {
+        let invalid = |kind: ContractKind| DecodeError::Xdr(format!("not a {kind:?} envelope"));
+
+        match kind {
+            ContractKind::HealthChain => {
+                use soroban_sdk_22::xdr::{Limits, ReadXdr, ScVal};
+                use soroban_sdk_22::TryFromVal;
+                let scval = ScVal::from_xdr(value, Limits::none())
+                    .map_err(|err| DecodeError::Xdr(err.to_string()))?;
+                healthchain::EventEnvelope::try_from_val(&self.hc_env, &scval)
+                    .map(EventBody::HealthChain)
+                    .map_err(|_| invalid(kind))
+            }
+            ContractKind::Inventory | ContractKind::Requests => {
+                use soroban_sdk::TryFromVal;
+                let scval = soroban_sdk::xdr::ScVal::from_xdr(value, Limits::none())
+                    .map_err(|err| DecodeError::Xdr(err.to_string()))?;
+                if kind == ContractKind::Inventory {
+                    inventory_contract::EventEnvelope::try_from_val(&self.env, &scval)
+                        .map(EventBody::Inventory)
+                        .map_err(|_| invalid(kind))
+                } else {
+                    request_contract::EventEnvelope::try_from_val(&self.env, &scval)
+                        .map(EventBody::Requests)
+                        .map_err(|_| invalid(kind))
+                }
+            }
+        }
+    }
+}
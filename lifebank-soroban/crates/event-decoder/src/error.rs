@@ -0,0 +1,52 @@
+use core::fmt;
+
+/// Errors raised while reading or decoding an event dump
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The dump is not valid JSON
+    Json(serde_json::Error),
+
+    /// A topic or value is not valid base64
+    Base64(base64::DecodeError),
+
+    /// A topic or value is not valid XDR, or does not match any contract type
+    Xdr(String),
+
+    /// A required field is missing from a JSON event
+    MissingField(&'static str),
+
+    /// No known contract could decode the event; carries its topics
+    UnknownEvent(String),
+
+    /// The event envelope uses a schema version this crate does not understand
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Json(err) => write!(f, "invalid JSON: {err}"),
+            DecodeError::Base64(err) => write!(f, "invalid base64: {err}"),
+            DecodeError::Xdr(msg) => write!(f, "invalid XDR: {msg}"),
+            DecodeError::MissingField(field) => write!(f, "missing field `{field}`"),
+            DecodeError::UnknownEvent(topics) => write!(f, "unknown event {topics}"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported event schema version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(err: serde_json::Error) -> Self {
+        DecodeError::Json(err)
+    }
+}
+
+impl From<base64::DecodeError> for DecodeError {
+    fn from(err: base64::DecodeError) -> Self {
+        DecodeError::Base64(err)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::error::DecodeError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+use soroban_sdk::xdr::{
+    ContractEvent, ContractEventBody, Limits, ReadXdr, ScAddress, ScVal, WriteXdr,
+};
+
+/// One exported contract event with its value still XDR-encoded
+///
+/// The value is kept as raw `ScVal` bytes so that it can be decoded with the
+/// soroban-sdk version of whichever contract published it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawEvent {
+    /// RPC event id, when the dump carries one
+    pub id: Option<String>,
+
+    /// Ledger sequence the event was published in
+    pub ledger: Option<u32>,
+
+    /// Strkey of the publishing contract (`C...`)
+    pub contract_id: Option<String>,
+
+    /// Symbol topics, e.g. `["blood", "allocate"]`
+    pub topics: Vec<String>,
+
+    /// XDR-encoded `ScVal` event data
+    pub value: Vec<u8>,
+}
+
+/// Parse a JSON event dump
+///
+/// Accepts the `getEvents` RPC response (`{"result": {"events": [...]}}`), its
+/// `result` object, or a bare array of events. Each event needs a `topic` array
+/// and a `value`, both base64 XDR `ScVal`s; `value` may also be `{"xdr": "..."}`.
+pub fn parse_json(input: &str) -> Result<Vec<RawEvent>, DecodeError> {
+    let root: Value = serde_json::from_str(input)?;
+    let events = match &root {
+        Value::Array(events) => events,
+        Value::Object(map) => map
+            .get("result")
+            .unwrap_or(&root)
+            .get("events")
+            .and_then(Value::as_array)
+            .ok_or(DecodeError::MissingField("events"))?,
+        _ => return Err(DecodeError::MissingField("events")),
+    };
+
+    events.iter().map(parse_json_event).collect()
+}
+
+/// Parse an XDR event dump: one base64 `ContractEvent` per line
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_xdr(input: &str) -> Result<Vec<RawEvent>, DecodeError> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_xdr_event)
+        .collect()
+}
+
+fn parse_json_event(event: &Value) -> Result<RawEvent, DecodeError> {
+    let topics = event
+        .get("topic")
+        .or_else(|| event.get("topics"))
+        .and_then(Value::as_array)
+        .ok_or(DecodeError::MissingField("topic"))?
+        .iter()
+        .map(|topic| {
+            let encoded = topic.as_str().ok_or(DecodeError::MissingField("topic"))?;
+            let scval = ScVal::from_xdr(STANDARD.decode(encoded)?, Limits::none())
+                .map_err(|err| DecodeError::Xdr(err.to_string()))?;
+            topic_name(&scval)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let value = event
+        .get("value")
+        .ok_or(DecodeError::MissingField("value"))?;
+    let encoded = value
+        .as_str()
+        .or_else(|| value.get("xdr").and_then(Value::as_str))
+        .ok_or(DecodeError::MissingField("value"))?;
+
+    Ok(RawEvent {
+        id: event.get("id").and_then(Value::as_str).map(str::to_owned),
+        ledger: event
+            .get("ledger")
+            .and_then(Value::as_u64)
+            .map(|ledger| ledger as u32),
+        contract_id: event
+            .get("contractId")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        topics,
+        value: STANDARD.decode(encoded)?,
+    })
+}
+
+fn parse_xdr_event(line: &str) -> Result<RawEvent, DecodeError> {
+    let event = ContractEvent::from_xdr(STANDARD.decode(line)?, Limits::none())
+        .map_err(|err| DecodeError::Xdr(err.to_string()))?;
+    let ContractEventBody::V0(body) = event.body;
+
+    let topics = body
+        .topics
+        .iter()
+        .map(topic_name)
+        .collect::<Result<Vec<_>, _>>()?;
+    let value = body
+        .data
+        .to_xdr(Limits::none())
+        .map_err(|err| DecodeError::Xdr(err.to_string()))?;
+
+    Ok(RawEvent {
+        id: None,
+        ledger: None,
+        contract_id: event
+            .contract_id
+            .map(|id| ScAddress::Contract(id).to_string()),
+        topics,
+        value,
+    })
+}
+
+fn topic_name(topic: &ScVal) -> Result<String, DecodeError> {
+    match topic {
+        ScVal::Symbol(symbol) => Ok(String::from_utf8_lossy(symbol.as_slice()).into_owned()),
+        _ => Err(DecodeError::Xdr("event topic is not a symbol".into())),
+    }
+}
@@ -0,0 +1,29 @@
+//! Offline decoder for exported contract events
+//!
+//! Reads event dumps (RPC JSON or base64 XDR), decodes each event's
+//! `EventEnvelope` into the typed structs of `HealthChainContract`, the
+//! inventory contract or the requests contract, and rebuilds read models from
+//! them: current unit state, per-unit history, request timelines and stock
+//! levels. Nothing here talks to the network.
+//!
+//! ```ignore
+//! let decoder = Decoder::new().with_contract("CABC...", ContractKind::Inventory);
+//! let events = decoder.decode_all(parse_json(&dump)?)?;
+//! let projection = Projection::build(&decoder, &events);
+//! ```
+
+mod decode;
+mod error;
+mod input;
+mod projection;
+
+pub use crate::decode::{ContractKind, DecodedEvent, Decoder, EventBody};
+pub use crate::error::DecodeError;
+pub use crate::input::{parse_json, parse_xdr, RawEvent};
+pub use crate::projection::{
+    Projection, StockKey, StockLevel, TimelineEntry, TimelineKind, UnitState, UnitStatus,
+    UnitTransition,
+};
+
+#[cfg(test)]
+mod test;
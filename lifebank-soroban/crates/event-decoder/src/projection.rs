@@ -0,0 +1,413 @@
+use crate::decode::{ContractKind, DecodedEvent, Decoder, EventBody};
+use std::collections::BTreeMap;
+
+/// Lifecycle status of a blood unit, shared by both contract families
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UnitStatus {
+    Available,
+    Reserved,
+    InTransit,
+    Delivered,
+    Expired,
+    Discarded,
+}
+
+impl From<healthchain::BloodStatus> for UnitStatus {
+    fn from(status: healthchain::BloodStatus) -> Self {
+        match status {
+            healthchain::BloodStatus::Available => UnitStatus::Available,
+            healthchain::BloodStatus::Reserved => UnitStatus::Reserved,
+            healthchain::BloodStatus::InTransit => UnitStatus::InTransit,
+            healthchain::BloodStatus::Delivered => UnitStatus::Delivered,
+            healthchain::BloodStatus::Expired => UnitStatus::Expired,
+            healthchain::BloodStatus::Discarded => UnitStatus::Discarded,
+        }
+    }
+}
+
+impl From<inventory_contract::BloodStatus> for UnitStatus {
+    fn from(status: inventory_contract::BloodStatus) -> Self {
+        match status {
+            inventory_contract::BloodStatus::Available => UnitStatus::Available,
+            inventory_contract::BloodStatus::Reserved => UnitStatus::Reserved,
+            inventory_contract::BloodStatus::InTransit => UnitStatus::InTransit,
+            inventory_contract::BloodStatus::Delivered => UnitStatus::Delivered,
+            inventory_contract::BloodStatus::Expired => UnitStatus::Expired,
+        }
+    }
+}
+
+/// Current state of a blood unit rebuilt from its events
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnitState {
+    pub contract: ContractKind,
+    pub unit_id: u64,
+    pub bank: String,
+    pub blood_type: String,
+    pub quantity_ml: u32,
+    pub status: UnitStatus,
+    pub hospital: Option<String>,
+    pub registered_at: u64,
+    pub updated_at: u64,
+}
+
+/// One step in a unit's status history
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnitTransition {
+    /// Previous status (`None` for the registration itself)
+    pub from: Option<UnitStatus>,
+    pub to: UnitStatus,
+    pub actor: Option<String>,
+    pub timestamp: u64,
+}
+
+/// Kind of step in a request timeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelineKind {
+    Created,
+    StatusChanged,
+    UnitsAssigned,
+    UnitsReleased,
+    Escalated,
+    Overdue,
+}
+
+/// One step in a request's timeline
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub kind: TimelineKind,
+
+    /// New status or urgency, by variant name (e.g. `"Approved"`, `"Critical"`)
+    pub detail: Option<String>,
+    pub actor: Option<String>,
+    pub timestamp: u64,
+}
+
+/// Key of a stock total: one (bank, blood type, status) bucket per contract
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StockKey {
+    pub contract: ContractKind,
+    pub bank: String,
+    pub blood_type: String,
+    pub status: UnitStatus,
+}
+
+/// Stock total for a `StockKey`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StockLevel {
+    pub units: u32,
+    pub quantity_ml: u64,
+}
+
+/// Read models rebuilt offline from decoded events
+///
+/// Events must be applied in publication order. Units and requests are keyed
+/// by contract family because each contract numbers its records independently.
+#[derive(Clone, Debug, Default)]
+pub struct Projection {
+    units: BTreeMap<(ContractKind, u64), UnitState>,
+    history: BTreeMap<(ContractKind, u64), Vec<UnitTransition>>,
+    requests: BTreeMap<(ContractKind, u64), Vec<TimelineEntry>>,
+}
+
+impl Projection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a projection from a whole dump
+    pub fn build(decoder: &Decoder, events: &[DecodedEvent]) -> Self {
+        let mut projection = Self::new();
+        for event in events {
+            projection.apply(decoder, event);
+        }
+        projection
+    }
+
+    /// Current state of a unit, if it was registered in the dump
+    pub fn unit(&self, contract: ContractKind, unit_id: u64) -> Option<&UnitState> {
+        self.units.get(&(contract, unit_id))
+    }
+
+    /// All known units, ordered by contract then id
+    pub fn units(&self) -> impl Iterator<Item = &UnitState> {
+        self.units.values()
+    }
+
+    /// Status history of a unit, oldest first
+    pub fn unit_history(&self, contract: ContractKind, unit_id: u64) -> &[UnitTransition] {
+        self.history
+            .get(&(contract, unit_id))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Timeline of a request, oldest first
+    pub fn request_timeline(&self, contract: ContractKind, request_id: u64) -> &[TimelineEntry] {
+        self.requests
+            .get(&(contract, request_id))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Stock totals per (bank, blood type, status), derived from unit states
+    pub fn stock_levels(&self) -> BTreeMap<StockKey, StockLevel> {
+        let mut levels: BTreeMap<StockKey, StockLevel> = BTreeMap::new();
+        for unit in self.units.values() {
+            let key = StockKey {
+                contract: unit.contract,
+                bank: unit.bank.clone(),
+                blood_type: unit.blood_type.clone(),
+                status: unit.status,
+            };
+            let level = levels.entry(key).or_default();
+            level.units += 1;
+            level.quantity_ml += u64::from(unit.quantity_ml);
+        }
+        levels
+    }
+
+    /// Fold one event into the read models
+    pub fn apply(&mut self, decoder: &Decoder, event: &DecodedEvent) {
+        match &event.body {
+            EventBody::HealthChain(envelope) => self.apply_healthchain(decoder, envelope),
+            EventBody::Inventory(envelope) => self.apply_inventory(decoder, envelope),
+            EventBody::Requests(envelope) => self.apply_requests(decoder, envelope),
+        }
+    }
+
+    fn apply_healthchain(&mut self, decoder: &Decoder, envelope: &healthchain::EventEnvelope) {
+        use healthchain::EventPayload;
+
+        let env = decoder.hc_env();
+        let contract = ContractKind::HealthChain;
+        let actor = envelope.actor.as_ref().map(|a| hc_address(env, a));
+        let timestamp = envelope.timestamp;
+
+        match &envelope.payload {
+            EventPayload::BloodRegistered(event) => self.register_unit(UnitState {
+                contract,
+                unit_id: event.unit_id,
+                bank: hc_address(env, &event.bank_id),
+                blood_type: hc_blood_type(event.blood_type).into(),
+                quantity_ml: event.quantity_ml,
+                status: UnitStatus::Available,
+                hospital: None,
+                registered_at: event.registration_timestamp,
+                updated_at: timestamp,
+            }),
+            EventPayload::StatusChange(event) => self.move_unit(
+                contract,
+                event.blood_unit_id,
+                event.new_status.into(),
+                Some(hc_address(env, &event.actor)),
+                event.timestamp,
+            ),
+            EventPayload::UnitAllocated(event) => {
+                if let Some(unit) = self.units.get_mut(&(contract, event.unit_id)) {
+                    unit.hospital = Some(hc_address(env, &event.hospital));
+                }
+            }
+            EventPayload::UnitMoved(event) => {
+                if let Some(unit) = self.units.get_mut(&(contract, event.unit_id)) {
+                    unit.hospital = event.hospital.as_ref().map(|h| hc_address(env, h));
+                }
+            }
+            EventPayload::RequestCreated(event) => self.push_timeline(
+                contract,
+                event.request_id,
+                TimelineKind::Created,
+                Some(format!("{:?}", event.urgency)),
+                actor,
+                event.created_at,
+            ),
+            EventPayload::RequestStatusChange(event) => self.push_timeline(
+                contract,
+                event.request_id,
+                TimelineKind::StatusChanged,
+                Some(format!("{:?}", event.new_status)),
+                actor,
+                event.timestamp,
+            ),
+            EventPayload::UrgencyEscalated(event) => self.push_timeline(
+                contract,
+                event.request_id,
+                TimelineKind::Escalated,
+                Some(format!("{:?}", event.new_urgency)),
+                actor,
+                event.timestamp,
+            ),
+            EventPayload::RequestOverdue(event) => self.push_timeline(
+                contract,
+                event.request_id,
+                TimelineKind::Overdue,
+                None,
+                actor,
+                event.timestamp,
+            ),
+            _ => {}
+        }
+    }
+
+    fn apply_inventory(&mut self, decoder: &Decoder, envelope: &inventory_contract::EventEnvelope) {
+        use inventory_contract::EventPayload;
+
+        let env = decoder.env();
+        let contract = ContractKind::Inventory;
+
+        match &envelope.payload {
+            EventPayload::BloodRegistered(event) => self.register_unit(UnitState {
+                contract,
+                unit_id: event.blood_unit_id,
+                bank: address(env, &event.bank_id),
+                blood_type: format!("{:?}", event.blood_type),
+                quantity_ml: event.quantity_ml,
+                status: UnitStatus::Available,
+                hospital: None,
+                registered_at: event.registered_at,
+                updated_at: envelope.timestamp,
+            }),
+            EventPayload::StatusChange(event) => self.move_unit(
+                contract,
+                event.blood_unit_id,
+                event.to_status.into(),
+                Some(address(env, &event.authorized_by)),
+                event.changed_at,
+            ),
+            _ => {}
+        }
+    }
+
+    fn apply_requests(&mut self, decoder: &Decoder, envelope: &request_contract::EventEnvelope) {
+        use request_contract::EventPayload;
+
+        let env = decoder.env();
+        let contract = ContractKind::Requests;
+        let actor = envelope.actor.as_ref().map(|a| address(env, a));
+
+        let (request_id, kind, detail) = match &envelope.payload {
+            EventPayload::RequestCreated(event) => (
+                event.request_id,
+                TimelineKind::Created,
+                Some(format!("{:?}", event.urgency)),
+            ),
+            EventPayload::RequestStatusChanged(event) => (
+                event.request_id,
+                TimelineKind::StatusChanged,
+                Some(format!("{:?}", event.new_status)),
+            ),
+            EventPayload::UnitsAssigned(event) => {
+                (event.request_id, TimelineKind::UnitsAssigned, None)
+            }
+            EventPayload::UnitsReleased(event) => {
+                (event.request_id, TimelineKind::UnitsReleased, None)
+            }
+            EventPayload::UrgencyEscalated(event) => (
+                event.request_id,
+                TimelineKind::Escalated,
+                Some(format!("{:?}", event.new_urgency)),
+            ),
+            EventPayload::RequestOverdue(event) => (event.request_id, TimelineKind::Overdue, None),
+            _ => return,
+        };
+
+        self.push_timeline(
+            contract,
+            request_id,
+            kind,
+            detail,
+            actor,
+            envelope.timestamp,
+        );
+    }
+
+    fn register_unit(&mut self, unit: UnitState) {
+        let key = (unit.contract, unit.unit_id);
+        self.history.entry(key).or_default().push(UnitTransition {
+            from: None,
+            to: unit.status,
+            actor: Some(unit.bank.clone()),
+            timestamp: unit.registered_at,
+        });
+        self.units.insert(key, unit);
+    }
+
+    fn move_unit(
+        &mut self,
+        contract: ContractKind,
+        unit_id: u64,
+        status: UnitStatus,
+        actor: Option<String>,
+        timestamp: u64,
+    ) {
+        let key = (contract, unit_id);
+        let from = self.units.get_mut(&key).map(|unit| {
+            let from = unit.status;
+            unit.status = status;
+            unit.updated_at = timestamp;
+            from
+        });
+        self.history.entry(key).or_default().push(UnitTransition {
+            from,
+            to: status,
+            actor,
+            timestamp,
+        });
+    }
+
+    fn push_timeline(
+        &mut self,
+        contract: ContractKind,
+        request_id: u64,
+        kind: TimelineKind,
+        detail: Option<String>,
+        actor: Option<String>,
+        timestamp: u64,
+    ) {
+        self.requests
+            .entry((contract, request_id))
+            .or_default()
+            .push(TimelineEntry {
+                kind,
+                detail,
+                actor,
+                timestamp,
+            });
+    }
+}
+
+/// Strkey of a lifebank contract address
+fn address(env: &soroban_sdk::Env, address: &soroban_sdk::Address) -> String {
+    use soroban_sdk::{IntoVal, TryFromVal, Val};
+
+    let val: Val = address.into_val(env);
+    match soroban_sdk::xdr::ScVal::try_from_val(env, &val) {
+        Ok(soroban_sdk::xdr::ScVal::Address(address)) => address.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Strkey of a `HealthChainContract` address
+fn hc_address(env: &soroban_sdk_22::Env, address: &soroban_sdk_22::Address) -> String {
+    use soroban_sdk_22::{IntoVal, TryFromVal, Val};
+
+    let val: Val = address.into_val(env);
+    match soroban_sdk_22::xdr::ScVal::try_from_val(env, &val) {
+        Ok(soroban_sdk_22::xdr::ScVal::Address(address)) => address.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Variant name of a `HealthChainContract` blood type
+fn hc_blood_type(blood_type: healthchain::BloodType) -> &'static str {
+    match blood_type {
+        healthchain::BloodType::APositive => "APositive",
+        healthchain::BloodType::ANegative => "ANegative",
+        healthchain::BloodType::BPositive => "BPositive",
+        healthchain::BloodType::BNegative => "BNegative",
+        healthchain::BloodType::ABPositive => "ABPositive",
+        healthchain::BloodType::ABNegative => "ABNegative",
+        healthchain::BloodType::OPositive => "OPositive",
+        healthchain::BloodType::ONegative => "ONegative",
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    parse_json, parse_xdr, ContractKind, DecodeError, Decoder, EventBody, Projection, TimelineKind,
+    UnitStatus,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::json;
+
+use healthchain::{HealthChainContract, HealthChainContractClient};
+use inventory_contract::{InventoryContract, InventoryContractClient};
+use request_contract::{RequestContract, RequestContractClient};
+
+/// Append the last invocation's HealthChain events to a JSON dump
+fn export_hc_json(env: &soroban_sdk_22::Env, dump: &mut Vec<serde_json::Value>) {
+    use soroban_sdk_22::testutils::Events;
+    use soroban_sdk_22::xdr::{Limits, ScVal, WriteXdr};
+    use soroban_sdk_22::TryFromVal;
+
+    let encode = |val| {
+        let scval = ScVal::try_from_val(env, &val).unwrap();
+        STANDARD.encode(scval.to_xdr(Limits::none()).unwrap())
+    };
+
+    for (contract, topics, data) in env.events().all().iter() {
+        let contract = ScVal::try_from_val(env, &contract.to_val()).unwrap();
+        let ScVal::Address(contract) = contract else {
+            panic!("contract id is not an address");
+        };
+        let topics: Vec<String> = topics.iter().map(encode).collect();
+        dump.push(json!({
+            "contractId": contract.to_string(),
+            "topic": topics,
+            "value": encode(data),
+        }));
+    }
+}
+
+/// Append the last invocation's lifebank events to an XDR dump
+fn export_xdr(env: &soroban_sdk::Env, dump: &mut String) {
+    use soroban_sdk::testutils::Events;
+    use soroban_sdk::xdr::{
+        ContractEvent, ContractEventBody, ContractEventType, ContractEventV0, ExtensionPoint,
+        Limits, ScAddress, ScVal, WriteXdr,
+    };
+    use soroban_sdk::TryFromVal;
+
+    for (contract, topics, data) in env.events().all().iter() {
+        let ScAddress::Contract(contract_id) = ScAddress::from(&contract) else {
+            panic!("contract id is not a contract address");
+        };
+        let topics: Vec<ScVal> = topics
+            .iter()
+            .map(|topic| ScVal::try_from_val(env, &topic).unwrap())
+            .collect();
+        let event = ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: Some(contract_id),
+            type_: ContractEventType::Contract,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: topics.try_into().unwrap(),
+                data: ScVal::try_from_val(env, &data).unwrap(),
+            }),
+        };
+        dump.push_str(&STANDARD.encode(event.to_xdr(Limits::none()).unwrap()));
+        dump.push('\n');
+    }
+}
+
+#[test]
+fn test_healthchain_json_dump_rebuilds_unit_state() {
+    use soroban_sdk_22::testutils::{Address as _, Ledger};
+    use soroban_sdk_22::Address;
+
+    let env = soroban_sdk_22::Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(1000);
+    let contract_id = env.register(HealthChainContract, ());
+    let client = HealthChainContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let bank = Address::generate(&env);
+    let hospital = Address::generate(&env);
+
+    let mut dump = Vec::new();
+    client.initialize(&admin);
+    export_hc_json(&env, &mut dump);
+    client.register_blood_bank(&bank);
+    export_hc_json(&env, &mut dump);
+    client.register_hospital(&hospital);
+    export_hc_json(&env, &mut dump);
+
+    let expiration = 1000 + 7 * 86400;
+    let moved = client.register_blood(
+        &bank,
+        &healthchain::BloodType::ONegative,
+        &450,
+        &expiration,
+        &None,
+    );
+    export_hc_json(&env, &mut dump);
+    client.register_blood(
+        &bank,
+        &healthchain::BloodType::ONegative,
+        &300,
+        &expiration,
+        &None,
+    );
+    export_hc_json(&env, &mut dump);
+
+    env.ledger().set_timestamp(2000);
+    client.allocate_blood(&bank, &moved, &hospital);
+    export_hc_json(&env, &mut dump);
+    env.ledger().set_timestamp(3000);
+    client.initiate_transfer(&bank, &moved);
+    export_hc_json(&env, &mut dump);
+
+    let dump = json!({ "result": { "events": dump } }).to_string();
+    let decoder = Decoder::new();
+    let events = decoder.decode_all(parse_json(&dump).unwrap()).unwrap();
+    assert!(events
+        .iter()
+        .all(|event| event.body.kind() == ContractKind::HealthChain));
+
+    let projection = Projection::build(&decoder, &events);
+    let unit = projection.unit(ContractKind::HealthChain, moved).unwrap();
+    assert_eq!(unit.status, UnitStatus::InTransit);
+    assert_eq!(unit.blood_type, "ONegative");
+    assert_eq!(unit.updated_at, 3000);
+    assert!(unit.hospital.is_some());
+
+    let history = projection.unit_history(ContractKind::HealthChain, moved);
+    let statuses: Vec<UnitStatus> = history.iter().map(|step| step.to).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            UnitStatus::Available,
+            UnitStatus::Reserved,
+            UnitStatus::InTransit
+        ]
+    );
+    assert_eq!(history[1].from, Some(UnitStatus::Available));
+
+    let levels = projection.stock_levels();
+    let available: Vec<_> = levels
+        .iter()
+        .filter(|(key, _)| key.status == UnitStatus::Available)
+        .collect();
+    assert_eq!(available.len(), 1);
+    assert_eq!(available[0].1.units, 1);
+    assert_eq!(available[0].1.quantity_ml, 300);
+}
+
+#[test]
+fn test_lifebank_xdr_dump_rebuilds_units_and_timelines() {
+    use soroban_sdk::testutils::{Address as _, Ledger};
+    use soroban_sdk::{Address, String};
+
+    let env = soroban_sdk::Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(1000);
+
+    let inventory_id = env.register(InventoryContract, ());
+    let inventory = InventoryContractClient::new(&env, &inventory_id);
+    let requests_id = env.register(RequestContract, ());
+    let requests = RequestContractClient::new(&env, &requests_id);
+    let admin = Address::generate(&env);
+    let hospital = Address::generate(&env);
+
+    let mut dump = std::string::String::new();
+    inventory.initialize(&admin);
+    export_xdr(&env, &mut dump);
+    let unit_id = inventory.register_blood(
+        &admin,
+        &inventory_contract::BloodType::APositive,
+        &450u32,
+        &(1000 + 30 * 86400),
+        &None,
+    );
+    export_xdr(&env, &mut dump);
+    env.ledger().set_timestamp(1500);
+    inventory.update_status(
+        &unit_id,
+        &inventory_contract::BloodStatus::Reserved,
+        &admin,
+        &None,
+    );
+    export_xdr(&env, &mut dump);
+
+    requests.initialize(&admin);
+    export_xdr(&env, &mut dump);
+    requests.authorize_hospital(&hospital);
+    export_xdr(&env, &mut dump);
+    let request_id = requests.create_request(
+        &hospital,
+        &request_contract::BloodType::APositive,
+        &450u32,
+        &request_contract::UrgencyLevel::Normal,
+        &(1500 + 2 * 86400),
+        &String::from_str(&env, "Ward 3"),
+        &Address::generate(&env),
+        &String::from_str(&env, "Surgery"),
+        &String::from_str(&env, "Notes"),
+    );
+    export_xdr(&env, &mut dump);
+    requests.cancel_request(&request_id, &hospital);
+    export_xdr(&env, &mut dump);
+
+    let inventory_strkey = soroban_sdk::xdr::ScAddress::from(&inventory_id).to_string();
+    let requests_strkey = soroban_sdk::xdr::ScAddress::from(&requests_id).to_string();
+    let decoder = Decoder::new()
+        .with_contract(&inventory_strkey, ContractKind::Inventory)
+        .with_contract(&requests_strkey, ContractKind::Requests);
+    let events = decoder.decode_all(parse_xdr(&dump).unwrap()).unwrap();
+    assert!(matches!(events[0].body, EventBody::Inventory(_)));
+    assert_eq!(events[0].raw.topics, vec!["initialized".to_owned()]);
+
+    let projection = Projection::build(&decoder, &events);
+    let unit = projection.unit(ContractKind::Inventory, unit_id).unwrap();
+    assert_eq!(unit.status, UnitStatus::Reserved);
+    assert_eq!(unit.updated_at, 1500);
+
+    let timeline = projection.request_timeline(ContractKind::Requests, request_id);
+    assert_eq!(timeline.len(), 2);
+    assert_eq!(timeline[0].kind, TimelineKind::Created);
+    assert_eq!(timeline[1].kind, TimelineKind::StatusChanged);
+    assert_eq!(timeline[1].detail.as_deref(), Some("Cancelled"));
+    assert_eq!(
+        timeline[1].actor,
+        Some(soroban_sdk::xdr::ScAddress::from(&hospital).to_string())
+    );
+}
+
+#[test]
+fn test_rejects_malformed_and_foreign_events() {
+    assert!(matches!(
+        parse_json("{\"result\": {}}"),
+        Err(DecodeError::MissingField("events"))
+    ));
+    assert!(matches!(
+        parse_json("[{\"topic\": [\"%%\"], \"value\": \"\"}]"),
+        Err(DecodeError::Base64(_))
+    ));
+
+    // A symbol topic with a plain u32 value matches no envelope
+    let topic = STANDARD.encode([0, 0, 0, 15, 0, 0, 0, 4, b't', b'e', b's', b't']);
+    let value = STANDARD.encode([0, 0, 0, 3, 0, 0, 0, 7]);
+    let dump = json!([{ "topic": [topic], "value": { "xdr": value } }]).to_string();
+    let raws = parse_json(&dump).unwrap();
+    assert_eq!(raws[0].topics, vec!["test".to_owned()]);
+    assert!(matches!(
+        Decoder::new().decode(raws[0].clone()),
+        Err(DecodeError::UnknownEvent(topics)) if topics == "test"
+    ));
+}
@@ -0,0 +1,205 @@
+use crate::scenario::{BloodGroup, Urgency};
+use crate::target::{settle, Cost, Target};
+use healthchain::{BloodType, HealthChainContract, HealthChainContractClient, UrgencyLevel};
+use soroban_sdk_22::testutils::{Address as _, EnvTestConfig, Ledger};
+use soroban_sdk_22::{Address, Env, Vec};
+use std::collections::BTreeMap;
+
+/// `HealthChainContract` deployed in a local soroban-sdk 22 host
+pub struct HealthChainTarget {
+    env: Env,
+    contract_id: Address,
+    banks: BTreeMap<String, Address>,
+    hospitals: BTreeMap<String, Address>,
+    cost: Cost,
+}
+
+impl HealthChainTarget {
+    pub fn new() -> Self {
+        let env = Env::new_with_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        env.mock_all_auths();
+        env.cost_estimate().budget().reset_unlimited();
+
+        let contract_id = env.register(HealthChainContract, ());
+        let admin = Address::generate(&env);
+        HealthChainContractClient::new(&env, &contract_id).initialize(&admin);
+
+        HealthChainTarget {
+            env,
+            contract_id,
+            banks: BTreeMap::new(),
+            hospitals: BTreeMap::new(),
+            cost: Cost::default(),
+        }
+    }
+
+    fn client(&self) -> HealthChainContractClient<'_> {
+        HealthChainContractClient::new(&self.env, &self.contract_id)
+    }
+
+    fn metered<T>(&mut self, call: impl FnOnce(&Self) -> Result<T, String>) -> Result<T, String> {
+        self.env.cost_estimate().budget().reset_default();
+        let result = call(self);
+        let budget = self.env.cost_estimate().budget();
+        self.cost = Cost {
+            cpu: budget.cpu_instruction_cost(),
+            mem: budget.memory_bytes_cost(),
+        };
+        result
+    }
+
+    fn bank(&self, name: &str) -> Result<Address, String> {
+        self.banks
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown bank `{name}`"))
+    }
+
+    fn hospital(&self, name: &str) -> Result<Address, String> {
+        self.hospitals
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown hospital `{name}`"))
+    }
+}
+
+impl Default for HealthChainTarget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Target for HealthChainTarget {
+    fn name(&self) -> &'static str {
+        "healthchain"
+    }
+
+    fn add_bank(&mut self, bank: &str) {
+        self.env.cost_estimate().budget().reset_unlimited();
+        let address = Address::generate(&self.env);
+        self.client().register_blood_bank(&address);
+        self.banks.insert(bank.to_owned(), address);
+    }
+
+    fn add_hospital(&mut self, hospital: &str) {
+        self.env.cost_estimate().budget().reset_unlimited();
+        let address = Address::generate(&self.env);
+        self.client().register_hospital(&address);
+        self.hospitals.insert(hospital.to_owned(), address);
+    }
+
+    fn set_time(&mut self, timestamp: u64) {
+        self.env.ledger().set_timestamp(timestamp);
+    }
+
+    fn donate(
+        &mut self,
+        bank: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        expires_at: u64,
+    ) -> Result<u64, String> {
+        self.metered(|target| {
+            let bank = target.bank(bank)?;
+            settle(target.client().try_register_blood(
+                &bank,
+                &blood_type_of(blood_type),
+                &quantity_ml,
+                &expires_at,
+                &None,
+            ))
+        })
+    }
+
+    fn request(
+        &mut self,
+        hospital: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        urgency: Urgency,
+        required_by: u64,
+    ) -> Result<u64, String> {
+        self.metered(|target| {
+            let hospital = target.hospital(hospital)?;
+            let address = soroban_sdk_22::String::from_str(&target.env, "Simulated ward");
+            settle(target.client().try_create_request(
+                &hospital,
+                &blood_type_of(blood_type),
+                &quantity_ml,
+                &urgency_of(urgency),
+                &required_by,
+                &address,
+            ))
+        })
+    }
+
+    fn allocate(&mut self, bank: &str, unit_id: u64, hospital: &str) -> Result<(), String> {
+        self.metered(|target| {
+            let bank = target.bank(bank)?;
+            let hospital = target.hospital(hospital)?;
+            settle(
+                target
+                    .client()
+                    .try_allocate_blood(&bank, &unit_id, &hospital),
+            )
+        })
+    }
+
+    fn assign(&mut self, _request_id: u64, _unit_ids: &[u64]) -> Option<Result<(), String>> {
+        None
+    }
+
+    fn transfer(&mut self, bank: &str, unit_id: u64) -> Result<(), String> {
+        self.metered(|target| {
+            let bank = target.bank(bank)?;
+            settle(target.client().try_initiate_transfer(&bank, &unit_id))
+        })
+    }
+
+    fn deliver(&mut self, hospital: &str, unit_id: u64) -> Result<(), String> {
+        self.metered(|target| {
+            let hospital = target.hospital(hospital)?;
+            settle(target.client().try_confirm_delivery(&hospital, &unit_id))
+        })
+    }
+
+    fn expire(&mut self, unit_ids: &[u64]) -> Result<(), String> {
+        self.metered(|target| {
+            let unit_ids = Vec::from_slice(&target.env, unit_ids);
+            settle(target.client().try_expire_blood_units(&unit_ids)).map(|_| ())
+        })
+    }
+
+    fn escalate(&mut self, cursor: u64) -> Result<u64, String> {
+        self.metered(|target| settle(target.client().try_escalate_requests(&cursor, &100)))
+    }
+
+    fn last_cost(&self) -> Cost {
+        self.cost
+    }
+}
+
+fn blood_type_of(group: BloodGroup) -> BloodType {
+    match group {
+        BloodGroup::APositive => BloodType::APositive,
+        BloodGroup::ANegative => BloodType::ANegative,
+        BloodGroup::BPositive => BloodType::BPositive,
+        BloodGroup::BNegative => BloodType::BNegative,
+        BloodGroup::ABPositive => BloodType::ABPositive,
+        BloodGroup::ABNegative => BloodType::ABNegative,
+        BloodGroup::OPositive => BloodType::OPositive,
+        BloodGroup::ONegative => BloodType::ONegative,
+    }
+}
+
+fn urgency_of(urgency: Urgency) -> UrgencyLevel {
+    match urgency {
+        Urgency::Low => UrgencyLevel::Low,
+        Urgency::Routine => UrgencyLevel::Routine,
+        Urgency::High => UrgencyLevel::High,
+        Urgency::Urgent => UrgencyLevel::Urgent,
+        Urgency::Critical => UrgencyLevel::Critical,
+    }
+}
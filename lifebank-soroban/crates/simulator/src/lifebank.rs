@@ -0,0 +1,233 @@
+use crate::scenario::{BloodGroup, Urgency};
+use crate::target::{settle, Cost, Target};
+use inventory_contract::{BloodStatus, InventoryContract, InventoryContractClient};
+use request_contract::{RequestContract, RequestContractClient, UrgencyLevel};
+use soroban_sdk::testutils::{Address as _, EnvTestConfig, Ledger};
+use soroban_sdk::{Address, Env, Vec};
+use std::collections::BTreeMap;
+
+/// The lifebank inventory and requests contracts deployed in a local host
+///
+/// The inventory contract only accepts its admin as a blood bank, so every
+/// scenario bank acts through the admin account; per-bank ownership is still
+/// tracked by the simulator itself.
+pub struct LifebankTarget {
+    env: Env,
+    admin: Address,
+    inventory_id: Address,
+    requests_id: Address,
+    hospitals: BTreeMap<String, Address>,
+    cost: Cost,
+}
+
+impl LifebankTarget {
+    pub fn new() -> Self {
+        let env = Env::new_with_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        env.mock_all_auths();
+        env.cost_estimate().budget().reset_unlimited();
+
+        let admin = Address::generate(&env);
+        let inventory_id = env.register(InventoryContract, ());
+        let requests_id = env.register(RequestContract, ());
+        InventoryContractClient::new(&env, &inventory_id).initialize(&admin);
+        RequestContractClient::new(&env, &requests_id).initialize(&admin);
+
+        LifebankTarget {
+            env,
+            admin,
+            inventory_id,
+            requests_id,
+            hospitals: BTreeMap::new(),
+            cost: Cost::default(),
+        }
+    }
+
+    fn inventory(&self) -> InventoryContractClient<'_> {
+        InventoryContractClient::new(&self.env, &self.inventory_id)
+    }
+
+    fn requests(&self) -> RequestContractClient<'_> {
+        RequestContractClient::new(&self.env, &self.requests_id)
+    }
+
+    fn metered<T>(&mut self, call: impl FnOnce(&Self) -> Result<T, String>) -> Result<T, String> {
+        self.env.cost_estimate().budget().reset_default();
+        let result = call(self);
+        let budget = self.env.cost_estimate().budget();
+        self.cost = Cost {
+            cpu: budget.cpu_instruction_cost(),
+            mem: budget.memory_bytes_cost(),
+        };
+        result
+    }
+
+    fn hospital(&self, name: &str) -> Result<Address, String> {
+        self.hospitals
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown hospital `{name}`"))
+    }
+
+    fn set_status(&self, unit_id: u64, status: BloodStatus) -> Result<(), String> {
+        settle(
+            self.inventory()
+                .try_update_status(&unit_id, &status, &self.admin, &None),
+        )
+        .map(|_| ())
+    }
+}
+
+impl Default for LifebankTarget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Target for LifebankTarget {
+    fn name(&self) -> &'static str {
+        "lifebank"
+    }
+
+    fn add_bank(&mut self, _bank: &str) {}
+
+    fn add_hospital(&mut self, hospital: &str) {
+        self.env.cost_estimate().budget().reset_unlimited();
+        let address = Address::generate(&self.env);
+        self.requests().authorize_hospital(&address);
+        self.hospitals.insert(hospital.to_owned(), address);
+    }
+
+    fn set_time(&mut self, timestamp: u64) {
+        self.env.ledger().set_timestamp(timestamp);
+    }
+
+    fn donate(
+        &mut self,
+        _bank: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        expires_at: u64,
+    ) -> Result<u64, String> {
+        self.metered(|target| {
+            settle(target.inventory().try_register_blood(
+                &target.admin,
+                &inventory_type_of(blood_type),
+                &quantity_ml,
+                &expires_at,
+                &None,
+            ))
+        })
+    }
+
+    fn request(
+        &mut self,
+        hospital: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        urgency: Urgency,
+        required_by: u64,
+    ) -> Result<u64, String> {
+        self.metered(|target| {
+            let hospital = target.hospital(hospital)?;
+            let patient = Address::generate(&target.env);
+            settle(target.requests().try_create_request(
+                &hospital,
+                &request_type_of(blood_type),
+                &quantity_ml,
+                &urgency_of(urgency),
+                &required_by,
+                &soroban_sdk::String::from_str(&target.env, "Simulated ward"),
+                &patient,
+                &soroban_sdk::String::from_str(&target.env, "Simulated procedure"),
+                &soroban_sdk::String::from_str(&target.env, ""),
+            ))
+        })
+    }
+
+    fn allocate(&mut self, _bank: &str, unit_id: u64, _hospital: &str) -> Result<(), String> {
+        self.metered(|target| target.set_status(unit_id, BloodStatus::Reserved))
+    }
+
+    fn assign(&mut self, request_id: u64, unit_ids: &[u64]) -> Option<Result<(), String>> {
+        Some(self.metered(|target| {
+            let unit_ids = Vec::from_slice(&target.env, unit_ids);
+            settle(
+                target
+                    .requests()
+                    .try_assign_blood_units(&request_id, &unit_ids),
+            )
+        }))
+    }
+
+    fn transfer(&mut self, _bank: &str, unit_id: u64) -> Result<(), String> {
+        self.metered(|target| target.set_status(unit_id, BloodStatus::InTransit))
+    }
+
+    fn deliver(&mut self, hospital: &str, unit_id: u64) -> Result<(), String> {
+        self.metered(|target| {
+            let location = soroban_sdk::String::from_str(&target.env, hospital);
+            settle(
+                target
+                    .inventory()
+                    .try_mark_delivered(&unit_id, &target.admin, &location),
+            )
+            .map(|_| ())
+        })
+    }
+
+    fn expire(&mut self, unit_ids: &[u64]) -> Result<(), String> {
+        self.metered(|target| {
+            for unit_id in unit_ids {
+                settle(target.inventory().try_mark_expired(unit_id, &target.admin))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn escalate(&mut self, cursor: u64) -> Result<u64, String> {
+        self.metered(|target| settle(target.requests().try_escalate_requests(&cursor, &100)))
+    }
+
+    fn last_cost(&self) -> Cost {
+        self.cost
+    }
+}
+
+fn inventory_type_of(group: BloodGroup) -> inventory_contract::BloodType {
+    use inventory_contract::BloodType;
+    match group {
+        BloodGroup::APositive => BloodType::APositive,
+        BloodGroup::ANegative => BloodType::ANegative,
+        BloodGroup::BPositive => BloodType::BPositive,
+        BloodGroup::BNegative => BloodType::BNegative,
+        BloodGroup::ABPositive => BloodType::ABPositive,
+        BloodGroup::ABNegative => BloodType::ABNegative,
+        BloodGroup::OPositive => BloodType::OPositive,
+        BloodGroup::ONegative => BloodType::ONegative,
+    }
+}
+
+fn request_type_of(group: BloodGroup) -> request_contract::BloodType {
+    use request_contract::BloodType;
+    match group {
+        BloodGroup::APositive => BloodType::APositive,
+        BloodGroup::ANegative => BloodType::ANegative,
+        BloodGroup::BPositive => BloodType::BPositive,
+        BloodGroup::BNegative => BloodType::BNegative,
+        BloodGroup::ABPositive => BloodType::ABPositive,
+        BloodGroup::ABNegative => BloodType::ABNegative,
+        BloodGroup::OPositive => BloodType::OPositive,
+        BloodGroup::ONegative => BloodType::ONegative,
+    }
+}
+
+/// The requests contract has three levels; low and routine map to normal
+fn urgency_of(urgency: Urgency) -> UrgencyLevel {
+    match urgency {
+        Urgency::Low | Urgency::Routine => UrgencyLevel::Normal,
+        Urgency::High | Urgency::Urgent => UrgencyLevel::Urgent,
+        Urgency::Critical => UrgencyLevel::Critical,
+    }
+}
@@ -0,0 +1,115 @@
+//! Workload simulator for the blood supply-chain contracts
+//!
+//! Replays a scenario file (YAML or CSV) of donations, requests, allocations,
+//! transfers, deliveries and clock advances against `HealthChainContract` and
+//! the lifebank contracts in a local soroban test host, then reports the CPU
+//! and memory budget of every operation, the failures, wastage and request
+//! fulfillment. Operations whose cost keeps growing over the run are flagged.
+//!
+//! ```text
+//! simulator scenarios/city.yaml [--target healthchain|lifebank|both] [--strict]
+//! ```
+//!
+//! With `--strict` the process exits non-zero when any step fails or any
+//! operation's cost grows past the warning ratio.
+
+mod healthchain;
+mod lifebank;
+mod report;
+mod runner;
+mod scenario;
+mod target;
+
+use crate::healthchain::HealthChainTarget;
+use crate::lifebank::LifebankTarget;
+use crate::report::Report;
+use crate::runner::Runner;
+use crate::scenario::Scenario;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: simulator <scenario.yaml|scenario.csv> [--target healthchain|lifebank|both] [--strict]";
+
+struct Args {
+    scenario: PathBuf,
+    healthchain: bool,
+    lifebank: bool,
+    strict: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut scenario = None;
+    let mut target = "both".to_owned();
+    let mut strict = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target = args.next().ok_or("`--target` needs a value")?,
+            "--strict" => strict = true,
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if scenario.is_none() => scenario = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+
+    let (healthchain, lifebank) = match target.as_str() {
+        "healthchain" => (true, false),
+        "lifebank" => (false, true),
+        "both" => (true, true),
+        _ => return Err(format!("unknown target `{target}`")),
+    };
+    Ok(Args {
+        scenario: scenario.ok_or("missing scenario file")?,
+        healthchain,
+        lifebank,
+        strict,
+    })
+}
+
+/// Replay a scenario against every selected target
+fn simulate(scenario: &Scenario, healthchain: bool, lifebank: bool) -> Vec<Report> {
+    let mut reports = Vec::new();
+    if healthchain {
+        reports.push(Runner::new(&mut HealthChainTarget::new()).run(scenario));
+    }
+    if lifebank {
+        reports.push(Runner::new(&mut LifebankTarget::new()).run(scenario));
+    }
+    reports
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let scenario = match Scenario::load(&args.scenario) {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            eprintln!("{}: {err}", args.scenario.display());
+            return ExitCode::from(2);
+        }
+    };
+
+    let reports = simulate(&scenario, args.healthchain, args.lifebank);
+    let mut clean = true;
+    for report in &reports {
+        println!("{report}");
+        clean &= report.failures.is_empty() && report.growing_ops().is_empty();
+    }
+
+    if args.strict && !clean {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod test;
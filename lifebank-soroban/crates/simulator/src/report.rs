@@ -0,0 +1,198 @@
+use crate::target::Cost;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Growth ratio above which an operation is flagged as scaling with inventory
+pub const GROWTH_WARNING: f64 = 1.5;
+
+/// Fewest successful calls needed before growth is estimated
+const MIN_GROWTH_SAMPLES: usize = 8;
+
+/// Budget use of one operation across a run
+#[derive(Clone, Debug, Default)]
+pub struct OpStats {
+    pub calls: u32,
+    pub failures: u32,
+
+    /// Cost of each successful call, in call order
+    pub costs: Vec<Cost>,
+}
+
+impl OpStats {
+    pub fn record(&mut self, cost: Cost, ok: bool) {
+        self.calls += 1;
+        if ok {
+            self.costs.push(cost);
+        } else {
+            self.failures += 1;
+        }
+    }
+
+    pub fn cpu_avg(&self) -> u64 {
+        average(self.costs.iter().map(|cost| cost.cpu))
+    }
+
+    pub fn cpu_max(&self) -> u64 {
+        self.costs.iter().map(|cost| cost.cpu).max().unwrap_or(0)
+    }
+
+    pub fn mem_avg(&self) -> u64 {
+        average(self.costs.iter().map(|cost| cost.mem))
+    }
+
+    pub fn mem_max(&self) -> u64 {
+        self.costs.iter().map(|cost| cost.mem).max().unwrap_or(0)
+    }
+
+    /// Mean CPU of the last quarter of calls over the first quarter
+    ///
+    /// `None` until there are enough calls to compare.
+    pub fn growth(&self) -> Option<f64> {
+        if self.costs.len() < MIN_GROWTH_SAMPLES {
+            return None;
+        }
+        let quarter = self.costs.len() / 4;
+        let first = average(self.costs[..quarter].iter().map(|cost| cost.cpu));
+        let last = average(
+            self.costs[self.costs.len() - quarter..]
+                .iter()
+                .map(|cost| cost.cpu),
+        );
+        (first > 0).then(|| last as f64 / first as f64)
+    }
+}
+
+/// A step that failed, with the contract's error
+#[derive(Clone, Debug)]
+pub struct Failure {
+    /// Zero-based index of the scenario step
+    pub step: usize,
+    pub op: &'static str,
+    pub error: String,
+}
+
+/// Outcome of replaying one scenario against one target
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub scenario: String,
+    pub target: &'static str,
+    pub steps: usize,
+
+    /// Ledger seconds covered by the run
+    pub elapsed: u64,
+
+    pub ops: BTreeMap<&'static str, OpStats>,
+    pub failures: Vec<Failure>,
+
+    pub units_donated: u32,
+    pub ml_donated: u64,
+    pub units_expired: u32,
+    pub ml_expired: u64,
+
+    pub requests: u32,
+    pub requests_fulfilled: u32,
+    pub requests_on_time: u32,
+    pub ml_requested: u64,
+    pub ml_delivered: u64,
+}
+
+impl Report {
+    /// Operations whose cost grows past [`GROWTH_WARNING`] over the run
+    pub fn growing_ops(&self) -> Vec<&'static str> {
+        self.ops
+            .iter()
+            .filter(|(_, stats)| {
+                stats
+                    .growth()
+                    .is_some_and(|growth| growth >= GROWTH_WARNING)
+            })
+            .map(|(op, _)| *op)
+            .collect()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "scenario `{}` on {}: {} steps over {:.1} days",
+            self.scenario,
+            self.target,
+            self.steps,
+            self.elapsed as f64 / 86_400.0
+        )?;
+        writeln!(
+            f,
+            "  {:<10} {:>6} {:>6} {:>12} {:>12} {:>10} {:>10} {:>7}",
+            "operation", "calls", "failed", "cpu avg", "cpu max", "mem avg", "mem max", "growth"
+        )?;
+        for (op, stats) in &self.ops {
+            let growth = match stats.growth() {
+                Some(growth) if growth >= GROWTH_WARNING => format!("{growth:.2}!"),
+                Some(growth) => format!("{growth:.2}"),
+                None => "-".to_owned(),
+            };
+            writeln!(
+                f,
+                "  {:<10} {:>6} {:>6} {:>12} {:>12} {:>10} {:>10} {:>7}",
+                op,
+                stats.calls,
+                stats.failures,
+                stats.cpu_avg(),
+                stats.cpu_max(),
+                stats.mem_avg(),
+                stats.mem_max(),
+                growth
+            )?;
+        }
+
+        writeln!(
+            f,
+            "  wastage: {} of {} units expired ({} of {} ml, {:.1}%)",
+            self.units_expired,
+            self.units_donated,
+            self.ml_expired,
+            self.ml_donated,
+            percent(self.ml_expired, self.ml_donated)
+        )?;
+        writeln!(
+            f,
+            "  fulfillment: {} of {} requests fulfilled, {} on time; {} of {} ml delivered ({:.1}%)",
+            self.requests_fulfilled,
+            self.requests,
+            self.requests_on_time,
+            self.ml_delivered,
+            self.ml_requested,
+            percent(self.ml_delivered, self.ml_requested)
+        )?;
+
+        if !self.failures.is_empty() {
+            writeln!(f, "  failures:")?;
+            for failure in &self.failures {
+                writeln!(
+                    f,
+                    "    step {} {}: {}",
+                    failure.step + 1,
+                    failure.op,
+                    failure.error
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn average(values: impl Iterator<Item = u64>) -> u64 {
+    let (sum, count) = values.fold((0u128, 0u128), |(sum, count), value| {
+        (sum + value as u128, count + 1)
+    });
+    sum.checked_div(count).unwrap_or(0) as u64
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
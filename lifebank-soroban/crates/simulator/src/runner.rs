@@ -0,0 +1,436 @@
+use crate::report::{Failure, Report};
+use crate::scenario::{BloodGroup, Op, Scenario, Step, Urgency};
+use crate::target::Target;
+use std::collections::BTreeMap;
+
+/// Ledger timestamp the scenario clock starts at
+pub const START_TIME: u64 = 1_700_000_000;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const DEFAULT_UNIT_ML: u32 = 450;
+const DEFAULT_SHELF_LIFE_DAYS: u64 = 35;
+const DEFAULT_DUE_IN_HOURS: u64 = 48;
+
+/// Largest batch passed to a single `expire` call
+const EXPIRE_BATCH: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnitStage {
+    Available,
+    Reserved,
+    InTransit,
+    Delivered,
+    Expired,
+}
+
+#[derive(Clone, Debug)]
+struct Unit {
+    id: u64,
+    bank: String,
+    blood_type: BloodGroup,
+    quantity_ml: u32,
+    expires_at: u64,
+    stage: UnitStage,
+    hospital: Option<String>,
+    request: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct Request {
+    id: u64,
+    hospital: String,
+    blood_type: BloodGroup,
+    quantity_ml: u32,
+    required_by: u64,
+    allocated_ml: u64,
+    delivered_ml: u64,
+    fulfilled_at: Option<u64>,
+    units: Vec<u64>,
+}
+
+/// Replays a scenario against one target, tracking unit and request state
+/// off-chain to decide which ids each step acts on
+pub struct Runner<'a, T: Target> {
+    target: &'a mut T,
+    now: u64,
+    units: Vec<Unit>,
+    requests: BTreeMap<String, Request>,
+    report: Report,
+}
+
+impl<'a, T: Target> Runner<'a, T> {
+    pub fn new(target: &'a mut T) -> Self {
+        Runner {
+            target,
+            now: START_TIME,
+            units: Vec::new(),
+            requests: BTreeMap::new(),
+            report: Report::default(),
+        }
+    }
+
+    /// Run every step of the scenario and summarise the outcome
+    pub fn run(mut self, scenario: &Scenario) -> Report {
+        self.report.scenario = scenario.name.clone();
+        self.report.target = self.target.name();
+        self.report.steps = scenario.steps.len();
+
+        self.target.set_time(self.now);
+        for bank in &scenario.banks {
+            self.target.add_bank(bank);
+        }
+        for hospital in &scenario.hospitals {
+            self.target.add_hospital(hospital);
+        }
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            if let Some(at) = step.at {
+                self.advance_to(START_TIME + at);
+            }
+            if let Err(error) = self.step(index, step) {
+                self.fail(index, step.op, error);
+            }
+        }
+
+        self.finish()
+    }
+
+    fn step(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        match step.op {
+            Op::Donate => self.donate(index, step),
+            Op::Request => self.request(index, step),
+            Op::Allocate => self.allocate(index, step),
+            Op::Transfer => self.transfer(index, step),
+            Op::Deliver => self.deliver(index, step),
+            Op::Advance => {
+                let seconds = step.seconds.ok_or("missing `seconds`")?;
+                self.advance_to(self.now + seconds);
+                Ok(())
+            }
+            Op::Expire => self.expire(index),
+            Op::Escalate => self.escalate(index),
+        }
+    }
+
+    fn donate(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        let bank = required(&step.bank, "bank")?;
+        let blood_type = required(&step.blood_type, "blood_type")?;
+        let quantity_ml = step.quantity_ml.unwrap_or(DEFAULT_UNIT_ML);
+        let shelf_life = step.shelf_life_days.unwrap_or(DEFAULT_SHELF_LIFE_DAYS);
+        let expires_at = self.now + shelf_life * SECONDS_PER_DAY;
+
+        for _ in 0..step.count.unwrap_or(1) {
+            let result = self
+                .target
+                .donate(&bank, blood_type, quantity_ml, expires_at);
+            if let Some(id) = self.metered(index, "donate", result) {
+                self.report.units_donated += 1;
+                self.report.ml_donated += quantity_ml as u64;
+                self.units.push(Unit {
+                    id,
+                    bank: bank.clone(),
+                    blood_type,
+                    quantity_ml,
+                    expires_at,
+                    stage: UnitStage::Available,
+                    hospital: None,
+                    request: None,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        let hospital = required(&step.hospital, "hospital")?;
+        let blood_type = required(&step.blood_type, "blood_type")?;
+        let quantity_ml = required(&step.quantity_ml, "quantity_ml")?;
+        let urgency = step.urgency.unwrap_or(Urgency::Routine);
+        let due_in_hours = step.due_in_hours.unwrap_or(DEFAULT_DUE_IN_HOURS);
+        let required_by = self.now + due_in_hours * 3_600;
+        let label = step
+            .request
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1));
+
+        let result = self
+            .target
+            .request(&hospital, blood_type, quantity_ml, urgency, required_by);
+        if let Some(id) = self.metered(index, "request", result) {
+            self.report.requests += 1;
+            self.report.ml_requested += quantity_ml as u64;
+            self.requests.insert(
+                label,
+                Request {
+                    id,
+                    hospital,
+                    blood_type,
+                    quantity_ml,
+                    required_by,
+                    allocated_ml: 0,
+                    delivered_ml: 0,
+                    fulfilled_at: None,
+                    units: Vec::new(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Reserve units for a hospital, oldest expiry first
+    ///
+    /// With a `request` label the blood type and hospital default to the
+    /// request's, and allocation stops once the requested volume is covered.
+    fn allocate(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        let bank = required(&step.bank, "bank")?;
+        let request = match &step.request {
+            Some(label) => Some((
+                label.clone(),
+                self.requests
+                    .get(label)
+                    .cloned()
+                    .ok_or_else(|| format!("unknown request `{label}`"))?,
+            )),
+            None => None,
+        };
+        let hospital = match (&step.hospital, &request) {
+            (Some(hospital), _) => hospital.clone(),
+            (None, Some((_, request))) => request.hospital.clone(),
+            (None, None) => return Err("missing `hospital`".into()),
+        };
+        let blood_type = match (step.blood_type, &request) {
+            (Some(blood_type), _) => blood_type,
+            (None, Some((_, request))) => request.blood_type,
+            (None, None) => return Err("missing `blood_type`".into()),
+        };
+
+        let mut candidates: Vec<usize> = (0..self.units.len())
+            .filter(|&i| {
+                let unit = &self.units[i];
+                unit.stage == UnitStage::Available
+                    && unit.bank == bank
+                    && unit.blood_type == blood_type
+                    && unit.expires_at > self.now
+            })
+            .collect();
+        candidates.sort_by_key(|&i| self.units[i].expires_at);
+
+        let limit = match (step.count, &request) {
+            (Some(count), _) => count as usize,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => 1,
+        };
+        let mut allocated = 0;
+        for i in candidates {
+            if allocated >= limit {
+                break;
+            }
+            if let Some((label, _)) = &request {
+                let request = &self.requests[label];
+                if request.allocated_ml >= request.quantity_ml as u64 {
+                    break;
+                }
+            }
+
+            let result = self.target.allocate(&bank, self.units[i].id, &hospital);
+            if self.metered(index, "allocate", result).is_none() {
+                continue;
+            }
+            allocated += 1;
+            let unit = &mut self.units[i];
+            unit.stage = UnitStage::Reserved;
+            unit.hospital = Some(hospital.clone());
+            if let Some((label, _)) = &request {
+                unit.request = Some(label.clone());
+                let request = self.requests.get_mut(label).expect("request exists");
+                request.allocated_ml += unit.quantity_ml as u64;
+                request.units.push(unit.id);
+            }
+        }
+
+        if allocated == 0 {
+            return Err(format!("no available {blood_type:?} units at `{bank}`"));
+        }
+        if let Some((label, _)) = &request {
+            let request = &self.requests[label];
+            if let Some(result) = self.target.assign(request.id, &request.units) {
+                let cost = self.target.last_cost();
+                self.record(index, "assign", cost, result);
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        let bank = required(&step.bank, "bank")?;
+        let limit = step.count.map_or(usize::MAX, |count| count as usize);
+
+        let picked: Vec<usize> = (0..self.units.len())
+            .filter(|&i| {
+                let unit = &self.units[i];
+                unit.stage == UnitStage::Reserved
+                    && unit.bank == bank
+                    && (step.hospital.is_none() || unit.hospital == step.hospital)
+                    && (step.request.is_none() || unit.request == step.request)
+            })
+            .take(limit)
+            .collect();
+        if picked.is_empty() {
+            return Err(format!("no reserved units at `{bank}`"));
+        }
+
+        for i in picked {
+            let result = self.target.transfer(&bank, self.units[i].id);
+            if self.metered(index, "transfer", result).is_some() {
+                self.units[i].stage = UnitStage::InTransit;
+            }
+        }
+        Ok(())
+    }
+
+    fn deliver(&mut self, index: usize, step: &Step) -> Result<(), String> {
+        let hospital = required(&step.hospital, "hospital")?;
+
+        let picked: Vec<usize> = (0..self.units.len())
+            .filter(|&i| {
+                let unit = &self.units[i];
+                unit.stage == UnitStage::InTransit && unit.hospital.as_ref() == Some(&hospital)
+            })
+            .collect();
+        if picked.is_empty() {
+            return Err(format!("no units in transit to `{hospital}`"));
+        }
+
+        for i in picked {
+            let result = self.target.deliver(&hospital, self.units[i].id);
+            if self.metered(index, "deliver", result).is_none() {
+                continue;
+            }
+            let unit = &mut self.units[i];
+            unit.stage = UnitStage::Delivered;
+            if let Some(request) = unit
+                .request
+                .as_ref()
+                .and_then(|label| self.requests.get_mut(label))
+            {
+                request.delivered_ml += unit.quantity_ml as u64;
+                if request.fulfilled_at.is_none()
+                    && request.delivered_ml >= request.quantity_ml as u64
+                {
+                    request.fulfilled_at = Some(self.now);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Expire every undelivered unit whose expiry date has passed
+    fn expire(&mut self, index: usize) -> Result<(), String> {
+        let due: Vec<usize> = (0..self.units.len())
+            .filter(|&i| {
+                let unit = &self.units[i];
+                unit.expires_at <= self.now
+                    && matches!(
+                        unit.stage,
+                        UnitStage::Available | UnitStage::Reserved | UnitStage::InTransit
+                    )
+            })
+            .collect();
+
+        for batch in due.chunks(EXPIRE_BATCH) {
+            let ids: Vec<u64> = batch.iter().map(|&i| self.units[i].id).collect();
+            let result = self.target.expire(&ids);
+            if self.metered(index, "expire", result).is_none() {
+                continue;
+            }
+            for &i in batch {
+                self.units[i].stage = UnitStage::Expired;
+                self.report.units_expired += 1;
+                self.report.ml_expired += self.units[i].quantity_ml as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn escalate(&mut self, index: usize) -> Result<(), String> {
+        let mut cursor = 0;
+        loop {
+            let result = self.target.escalate(cursor);
+            match self.metered(index, "escalate", result) {
+                Some(0) | None => return Ok(()),
+                Some(next) => cursor = next,
+            }
+        }
+    }
+
+    fn advance_to(&mut self, timestamp: u64) {
+        if timestamp > self.now {
+            self.now = timestamp;
+            self.target.set_time(timestamp);
+        }
+    }
+
+    /// Record the cost of the target's last operation and unwrap its result
+    fn metered<V>(
+        &mut self,
+        index: usize,
+        op: &'static str,
+        result: Result<V, String>,
+    ) -> Option<V> {
+        let cost = self.target.last_cost();
+        self.record(index, op, cost, result)
+    }
+
+    fn record<V>(
+        &mut self,
+        index: usize,
+        op: &'static str,
+        cost: crate::target::Cost,
+        result: Result<V, String>,
+    ) -> Option<V> {
+        self.report
+            .ops
+            .entry(op)
+            .or_default()
+            .record(cost, result.is_ok());
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.report.failures.push(Failure {
+                    step: index,
+                    op,
+                    error,
+                });
+                None
+            }
+        }
+    }
+
+    /// Record a step that was rejected before reaching the contract
+    fn fail(&mut self, index: usize, op: Op, error: String) {
+        let op = op.name();
+        self.report.failures.push(Failure {
+            step: index,
+            op,
+            error,
+        });
+    }
+
+    fn finish(mut self) -> Report {
+        self.report.elapsed = self.now - START_TIME;
+        for request in self.requests.values() {
+            self.report.ml_delivered += request.delivered_ml.min(request.quantity_ml as u64);
+            if let Some(fulfilled_at) = request.fulfilled_at {
+                self.report.requests_fulfilled += 1;
+                if fulfilled_at <= request.required_by {
+                    self.report.requests_on_time += 1;
+                }
+            }
+        }
+        self.report
+    }
+}
+
+fn required<V: Clone>(value: &Option<V>, field: &str) -> Result<V, String> {
+    value.clone().ok_or_else(|| format!("missing `{field}`"))
+}
@@ -0,0 +1,245 @@
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// A supply-chain workload: the participants and the steps to replay
+///
+/// Banks and hospitals that are only mentioned by steps are registered
+/// implicitly, so a CSV scenario needs nothing but its rows.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(default)]
+    pub banks: Vec<String>,
+
+    #[serde(default)]
+    pub hospitals: Vec<String>,
+
+    pub steps: Vec<Step>,
+}
+
+/// Operation performed by a scenario step
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    /// A bank registers `count` donated units
+    Donate,
+
+    /// A hospital opens a blood request
+    Request,
+
+    /// A bank reserves `count` available units for a hospital, oldest expiry first
+    Allocate,
+
+    /// A bank ships `count` reserved units
+    Transfer,
+
+    /// A hospital confirms receipt of every unit in transit to it
+    Deliver,
+
+    /// The ledger clock moves forward by `seconds`
+    Advance,
+
+    /// Every unit past its expiry date is expired on-chain
+    Expire,
+
+    /// The request escalation sweep runs over all requests
+    Escalate,
+}
+
+impl Op {
+    /// Name used in scenario files and reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Donate => "donate",
+            Op::Request => "request",
+            Op::Allocate => "allocate",
+            Op::Transfer => "transfer",
+            Op::Deliver => "deliver",
+            Op::Advance => "advance",
+            Op::Expire => "expire",
+            Op::Escalate => "escalate",
+        }
+    }
+}
+
+/// ABO/Rh blood group, written as `O-`, `AB+`, ...
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum BloodGroup {
+    #[serde(rename = "A+")]
+    APositive,
+    #[serde(rename = "A-")]
+    ANegative,
+    #[serde(rename = "B+")]
+    BPositive,
+    #[serde(rename = "B-")]
+    BNegative,
+    #[serde(rename = "AB+")]
+    ABPositive,
+    #[serde(rename = "AB-")]
+    ABNegative,
+    #[serde(rename = "O+")]
+    OPositive,
+    #[serde(rename = "O-")]
+    ONegative,
+}
+
+/// Request urgency; mapped onto each contract's own levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Urgency {
+    Low,
+    Routine,
+    High,
+    Urgent,
+    Critical,
+}
+
+/// One scenario step
+///
+/// YAML steps and CSV rows share this shape; fields an operation does not
+/// use are ignored, and missing required fields are reported as failures of
+/// that step.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Step {
+    /// Seconds since the start of the scenario; the clock never moves back
+    #[serde(default)]
+    pub at: Option<u64>,
+
+    pub op: Op,
+
+    #[serde(default)]
+    pub bank: Option<String>,
+
+    #[serde(default)]
+    pub hospital: Option<String>,
+
+    #[serde(default)]
+    pub blood_type: Option<BloodGroup>,
+
+    /// Volume per donated unit, or total volume of a request
+    #[serde(default)]
+    pub quantity_ml: Option<u32>,
+
+    /// Units to donate, allocate or transfer; donations and allocations
+    /// without a request default to 1, everything else to every eligible unit
+    #[serde(default)]
+    pub count: Option<u32>,
+
+    #[serde(default)]
+    pub urgency: Option<Urgency>,
+
+    /// Request deadline, relative to the step time
+    #[serde(default)]
+    pub due_in_hours: Option<u64>,
+
+    /// Shelf life of donated units
+    #[serde(default)]
+    pub shelf_life_days: Option<u64>,
+
+    /// Clock advance for `advance` steps
+    #[serde(default)]
+    pub seconds: Option<u64>,
+
+    /// Label of the request a step refers to; `request` steps define it
+    #[serde(default)]
+    pub request: Option<String>,
+}
+
+/// Errors raised while loading a scenario file
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Yaml(serde_yaml::Error),
+    Csv(csv::Error),
+
+    /// The file extension is neither YAML nor CSV
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "cannot read scenario: {err}"),
+            ScenarioError::Yaml(err) => write!(f, "invalid YAML scenario: {err}"),
+            ScenarioError::Csv(err) => write!(f, "invalid CSV scenario: {err}"),
+            ScenarioError::UnknownFormat(ext) => {
+                write!(
+                    f,
+                    "unknown scenario format `{ext}` (expected .yaml, .yml or .csv)"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    /// Load a scenario, picking the format from the file extension
+    pub fn load(path: &Path) -> Result<Scenario, ScenarioError> {
+        let input = std::fs::read_to_string(path).map_err(ScenarioError::Io)?;
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let mut scenario = match ext.as_str() {
+            "yaml" | "yml" => Scenario::from_yaml(&input)?,
+            "csv" => Scenario::from_csv(&input)?,
+            _ => return Err(ScenarioError::UnknownFormat(ext)),
+        };
+        if scenario.name.is_empty() {
+            scenario.name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("scenario")
+                .to_owned();
+        }
+        Ok(scenario)
+    }
+
+    /// Parse a YAML scenario with `name`, `banks`, `hospitals` and `steps`
+    pub fn from_yaml(input: &str) -> Result<Scenario, ScenarioError> {
+        let mut scenario: Scenario = serde_yaml::from_str(input).map_err(ScenarioError::Yaml)?;
+        scenario.collect_participants();
+        Ok(scenario)
+    }
+
+    /// Parse a CSV scenario: a header row naming `Step` fields, one step per row
+    pub fn from_csv(input: &str) -> Result<Scenario, ScenarioError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .comment(Some(b'#'))
+            .from_reader(input.as_bytes());
+        let steps = reader
+            .deserialize()
+            .collect::<Result<Vec<Step>, _>>()
+            .map_err(ScenarioError::Csv)?;
+
+        let mut scenario = Scenario {
+            steps,
+            ..Scenario::default()
+        };
+        scenario.collect_participants();
+        Ok(scenario)
+    }
+
+    fn collect_participants(&mut self) {
+        for step in &self.steps {
+            if let Some(bank) = &step.bank {
+                if !self.banks.contains(bank) {
+                    self.banks.push(bank.clone());
+                }
+            }
+            if let Some(hospital) = &step.hospital {
+                if !self.hospitals.contains(hospital) {
+                    self.hospitals.push(hospital.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::scenario::{BloodGroup, Urgency};
+use core::fmt::Debug;
+
+/// Budget consumed by one metered operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    /// CPU instructions charged by the host
+    pub cpu: u64,
+
+    /// Memory bytes charged by the host
+    pub mem: u64,
+}
+
+/// A contract deployment the simulator can drive
+///
+/// Each method performs one metered operation against a fresh default budget,
+/// so a call that would exceed network limits fails here as it would on-chain.
+/// The cost of the last operation is available from [`Target::last_cost`].
+/// Errors are the contract (or host) error rendered as text.
+pub trait Target {
+    /// Short name used in reports
+    fn name(&self) -> &'static str;
+
+    /// Register a blood bank under a scenario name
+    fn add_bank(&mut self, bank: &str);
+
+    /// Register a hospital under a scenario name
+    fn add_hospital(&mut self, hospital: &str);
+
+    /// Set the ledger timestamp
+    fn set_time(&mut self, timestamp: u64);
+
+    /// Register one donated unit, returning its unit id
+    fn donate(
+        &mut self,
+        bank: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        expires_at: u64,
+    ) -> Result<u64, String>;
+
+    /// Open a blood request, returning its request id
+    fn request(
+        &mut self,
+        hospital: &str,
+        blood_type: BloodGroup,
+        quantity_ml: u32,
+        urgency: Urgency,
+        required_by: u64,
+    ) -> Result<u64, String>;
+
+    /// Reserve an available unit for a hospital
+    fn allocate(&mut self, bank: &str, unit_id: u64, hospital: &str) -> Result<(), String>;
+
+    /// Record the full set of units reserved for a request
+    ///
+    /// Returns `None` when the deployment does not track assignments on-chain.
+    fn assign(&mut self, request_id: u64, unit_ids: &[u64]) -> Option<Result<(), String>>;
+
+    /// Ship a reserved unit
+    fn transfer(&mut self, bank: &str, unit_id: u64) -> Result<(), String>;
+
+    /// Confirm receipt of a unit in transit
+    fn deliver(&mut self, hospital: &str, unit_id: u64) -> Result<(), String>;
+
+    /// Expire a batch of units past their expiry date
+    fn expire(&mut self, unit_ids: &[u64]) -> Result<(), String>;
+
+    /// Run one page of the request escalation sweep, returning the next cursor
+    /// (0 once every request has been scanned)
+    fn escalate(&mut self, cursor: u64) -> Result<u64, String>;
+
+    /// Budget consumed by the last operation
+    fn last_cost(&self) -> Cost;
+}
+
+/// Flatten the nested result of a generated `try_` client call
+pub fn settle<T, C: Debug, E: Debug, I: Debug>(
+    result: Result<Result<T, C>, Result<E, I>>,
+) -> Result<T, String> {
+    match result {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(format!("conversion error: {err:?}")),
+        Err(Ok(err)) => Err(format!("{err:?}")),
+        Err(Err(err)) => Err(format!("host error: {err:?}")),
+    }
+}
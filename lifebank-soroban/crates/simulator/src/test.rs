@@ -0,0 +1,162 @@
+use crate::healthchain::HealthChainTarget;
+use crate::lifebank::LifebankTarget;
+use crate::report::{OpStats, Report};
+use crate::runner::Runner;
+use crate::scenario::{BloodGroup, Op, Scenario, ScenarioError, Urgency};
+use crate::simulate;
+use crate::target::Cost;
+use std::path::Path;
+
+const CITY_WEEK: &str = include_str!("../scenarios/city-week.yaml");
+const STEADY_DONATIONS: &str = include_str!("../scenarios/steady-donations.csv");
+
+fn run_healthchain(scenario: &Scenario) -> Report {
+    Runner::new(&mut HealthChainTarget::new()).run(scenario)
+}
+
+fn run_lifebank(scenario: &Scenario) -> Report {
+    Runner::new(&mut LifebankTarget::new()).run(scenario)
+}
+
+#[test]
+fn test_yaml_scenario_parses() {
+    let scenario = Scenario::from_yaml(CITY_WEEK).unwrap();
+
+    assert_eq!(scenario.name, "city-week");
+    assert_eq!(scenario.banks, ["central", "north"]);
+    assert_eq!(scenario.hospitals, ["general", "childrens"]);
+    assert_eq!(scenario.steps.len(), 27);
+
+    let trauma = &scenario.steps[4];
+    assert_eq!(trauma.op, Op::Request);
+    assert_eq!(trauma.blood_type, Some(BloodGroup::ONegative));
+    assert_eq!(trauma.urgency, Some(Urgency::Urgent));
+    assert_eq!(trauma.request.as_deref(), Some("trauma"));
+}
+
+#[test]
+fn test_csv_scenario_parses_and_collects_participants() {
+    let scenario = Scenario::from_csv(STEADY_DONATIONS).unwrap();
+
+    assert!(scenario.name.is_empty());
+    assert_eq!(scenario.banks, ["central"]);
+    assert_eq!(scenario.hospitals, ["general"]);
+    assert_eq!(scenario.steps.len(), 17);
+
+    let donate = &scenario.steps[0];
+    assert_eq!(donate.op, Op::Donate);
+    assert_eq!(donate.count, Some(20));
+    assert_eq!(donate.hospital, None);
+    assert_eq!(donate.request, None);
+}
+
+#[test]
+fn test_unknown_extension_is_rejected() {
+    let err = Scenario::load(Path::new("scenarios/city-week.json")).unwrap_err();
+    assert!(matches!(
+        err,
+        ScenarioError::Io(_) | ScenarioError::UnknownFormat(_)
+    ));
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    let err = Scenario::load(&path).unwrap_err();
+    assert!(matches!(err, ScenarioError::UnknownFormat(ext) if ext == "toml"));
+}
+
+#[test]
+fn test_city_week_on_healthchain() {
+    let scenario = Scenario::from_yaml(CITY_WEEK).unwrap();
+    let report = run_healthchain(&scenario);
+
+    assert!(report.failures.is_empty(), "{report}");
+    assert_eq!(report.units_donated, 30);
+    assert_eq!(report.units_expired, 4);
+    assert_eq!(report.ml_expired, 1_800);
+    assert_eq!(report.requests, 4);
+    assert_eq!(report.requests_fulfilled, 3);
+    assert_eq!(report.requests_on_time, 2);
+
+    let donate = &report.ops["donate"];
+    assert_eq!(donate.calls, 30);
+    assert!(donate.cpu_avg() > 0 && donate.mem_avg() > 0);
+    assert!(!report.ops.contains_key("assign"));
+}
+
+#[test]
+fn test_city_week_on_lifebank_matches_healthchain_outcome() {
+    let scenario = Scenario::from_yaml(CITY_WEEK).unwrap();
+    let healthchain = run_healthchain(&scenario);
+    let lifebank = run_lifebank(&scenario);
+
+    assert!(lifebank.failures.is_empty(), "{lifebank}");
+    assert_eq!(lifebank.units_donated, healthchain.units_donated);
+    assert_eq!(lifebank.ml_expired, healthchain.ml_expired);
+    assert_eq!(lifebank.requests_fulfilled, healthchain.requests_fulfilled);
+    assert_eq!(lifebank.ml_delivered, healthchain.ml_delivered);
+    assert_eq!(lifebank.ops["assign"].calls, 4);
+}
+
+#[test]
+fn test_csv_scenario_runs_on_both_targets() {
+    let scenario = Scenario::from_csv(STEADY_DONATIONS).unwrap();
+    let reports = simulate(&scenario, true, true);
+
+    assert_eq!(reports.len(), 2);
+    for report in &reports {
+        assert!(report.failures.is_empty(), "{report}");
+        assert_eq!(report.units_donated, 60);
+        assert_eq!(report.requests_fulfilled, 3);
+        assert_eq!(report.units_expired, 60 - 12);
+        assert_eq!(report.elapsed, 33 * 86_400);
+    }
+}
+
+#[test]
+fn test_invalid_steps_are_reported_as_failures() {
+    let scenario = Scenario::from_yaml(
+        "steps:
+  - { op: donate, bank: central }
+  - { op: request, hospital: general, blood_type: O-, quantity_ml: 10 }
+  - { op: allocate, bank: central, request: missing }
+  - { op: deliver, hospital: general }",
+    )
+    .unwrap();
+
+    for report in simulate(&scenario, true, true) {
+        let failed: Vec<_> = report
+            .failures
+            .iter()
+            .map(|failure| (failure.step, failure.op))
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                (0, "donate"),
+                (1, "request"),
+                (2, "allocate"),
+                (3, "deliver")
+            ]
+        );
+        assert_eq!(report.failures[0].error, "missing `blood_type`");
+        assert_eq!(report.ops["request"].failures, 1);
+    }
+}
+
+#[test]
+fn test_growth_compares_first_and_last_quarter() {
+    let mut stats = OpStats::default();
+    for cpu in [100, 100, 150, 150, 150, 150, 300, 300] {
+        stats.record(Cost { cpu, mem: 1 }, true);
+    }
+    stats.record(Cost::default(), false);
+
+    assert_eq!(stats.calls, 9);
+    assert_eq!(stats.failures, 1);
+    assert_eq!(stats.cpu_max(), 300);
+    assert_eq!(stats.growth(), Some(3.0));
+
+    let mut report = Report::default();
+    report.ops.insert("donate", stats);
+    report.ops.insert("deliver", OpStats::default());
+    assert_eq!(report.growing_ops(), ["donate"]);
+}